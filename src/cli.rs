@@ -0,0 +1,334 @@
+use std::{path::PathBuf, time::Duration};
+
+use stsr::{Error, Result, duration, log::Level, status::control};
+
+pub const USAGE: &str = "\
+Usage: stsr [OPTIONS]
+
+Options:
+      --config <PATH>           Read options from a file, one `key = value` per line
+      --separator <TEXT>        Text between statuses [default: \" \"]
+      --write-interval <TIME>   Time between bar writes, e.g. `500ms` [default: 1s]
+      --stdout, --no-stdout     Also print the bar to stdout [default: no]
+      --write-on-changes, --no-write-on-changes
+                                Write the bar only when it changed [default: yes]
+      --stdout-only             Print the bar to stdout instead of the X11 root window;
+                                X11 sources like `Keyboard` still connect
+      --control-socket <PATH>   Socket accepting commands, empty to disable
+                                [default: $XDG_RUNTIME_DIR/stsr.sock]
+      --log-level <LEVEL>       `error`, `warn`, `info`, `debug` or `trace` [default: info]
+      --log-file <PATH>         Log to a file instead of stderr, rotated at 1 MiB
+      --log-to <TARGET>         Log to `stderr`, `journald` or `syslog` [default: stderr]
+      --once                    Run every status once, print the bar and exit
+      --check                   Validate the configuration and exit
+      --list-sources            List the configured statuses and exit
+  -h, --help                    Print this help
+";
+
+/// What the binary does after parsing the options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Run,
+    Once,
+    Check,
+    ListSources,
+    Help,
+}
+
+/// Where logs are written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogTarget {
+    Stderr,
+    File(PathBuf),
+    Journald,
+    Syslog,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub separator: String,
+    pub write_interval: Duration,
+    pub write_to_stdout: bool,
+    pub write_on_changes: bool,
+    pub stdout_only: bool,
+    pub control_socket: Option<PathBuf>,
+    pub log_level: Level,
+    pub log_target: LogTarget,
+    pub mode: Mode,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            separator: String::from(" "),
+            write_interval: Duration::from_secs(1),
+            write_to_stdout: false,
+            write_on_changes: true,
+            stdout_only: false,
+            control_socket: control::default_path(),
+            log_level: Level::Info,
+            log_target: LogTarget::Stderr,
+            mode: Mode::Run,
+        }
+    }
+}
+
+/// Option name without the leading `--`, and its value if given.
+type Flag = (String, Option<String>);
+
+impl Options {
+    /// Parses the arguments after the program name; flags override the `--config` file.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let flags = flags(args)?;
+        let mut options = Self::default();
+
+        if let Some((_, Some(path))) = flags.iter().find(|(name, _)| name == "config") {
+            let contents = std::fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+            options.apply(config_file(&contents)?, false)?;
+        }
+        options.apply(flags, true)?;
+
+        Ok(options)
+    }
+
+    /// Applies `flags` in order; modes such as `once` are only accepted on the command line.
+    fn apply(&mut self, flags: Vec<Flag>, command_line: bool) -> Result<()> {
+        for (name, value) in flags {
+            match name.as_str() {
+                "config" if command_line => {}
+                "separator" => self.separator = required(&name, value)?,
+                "write-interval" => {
+                    self.write_interval = duration(&required(&name, value)?)?;
+                    if self.write_interval.is_zero() {
+                        return Err(Error::config("`write-interval` cannot be `0`"));
+                    }
+                }
+                "stdout" => self.write_to_stdout = boolean(&name, value)?,
+                "write-on-changes" => self.write_on_changes = boolean(&name, value)?,
+                "stdout-only" => self.stdout_only = boolean(&name, value)?,
+                "control-socket" => {
+                    let path = required(&name, value)?;
+                    self.control_socket = (!path.is_empty()).then(|| PathBuf::from(path));
+                }
+                "log-level" => self.log_level = required(&name, value)?.parse()?,
+                "log-file" => {
+                    self.log_target = LogTarget::File(PathBuf::from(required(&name, value)?));
+                }
+                "log-to" => {
+                    self.log_target = match required(&name, value)?.as_str() {
+                        "stderr" => LogTarget::Stderr,
+                        "journald" => LogTarget::Journald,
+                        "syslog" => LogTarget::Syslog,
+                        target => {
+                            return Err(Error::config(format!(
+                                "invalid log target `{target}`, expected `stderr`, `journald` or `syslog`"
+                            )));
+                        }
+                    };
+                }
+                "once" if command_line => self.mode = mode(&name, value, Mode::Once)?,
+                "check" if command_line => self.mode = mode(&name, value, Mode::Check)?,
+                "list-sources" if command_line => {
+                    self.mode = mode(&name, value, Mode::ListSources)?;
+                }
+                "help" if command_line => self.mode = mode(&name, value, Mode::Help)?,
+                _ => return Err(Error::config(format!("unknown option `{name}`"))),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Splits command line arguments into flags, taking the value of options that need one from
+/// `--name=value` or the next argument; `--no-name` turns off the switch `name`.
+fn flags(args: impl IntoIterator<Item = String>) -> Result<Vec<Flag>> {
+    let mut args = args.into_iter();
+    let mut flags = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if arg == "-h" {
+            flags.push((String::from("help"), None));
+            continue;
+        }
+        let Some(arg) = arg.strip_prefix("--") else {
+            return Err(Error::config(format!("unexpected argument `{arg}`")));
+        };
+
+        let flag = match arg.split_once('=') {
+            Some((name, value)) => (name.to_string(), Some(value.to_string())),
+            None if matches!(
+                arg,
+                "config"
+                    | "separator"
+                    | "write-interval"
+                    | "control-socket"
+                    | "log-level"
+                    | "log-file"
+                    | "log-to"
+            ) =>
+            {
+                let value = args
+                    .next()
+                    .ok_or_else(|| Error::config(format!("option `--{arg}` needs a value")))?;
+                (arg.to_string(), Some(value))
+            }
+            None => match arg.strip_prefix("no-") {
+                Some(name @ ("stdout" | "write-on-changes" | "stdout-only")) => {
+                    (name.to_string(), Some(String::from("false")))
+                }
+                _ => (arg.to_string(), None),
+            },
+        };
+        flags.push(flag);
+    }
+
+    Ok(flags)
+}
+
+/// Parses `key = value` lines; empty lines and lines starting with `#` are skipped, and values
+/// may be wrapped in double quotes to keep surrounding spaces.
+fn config_file(contents: &str) -> Result<Vec<Flag>> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| Error::config(format!("expected `key = value`, got `{line}`")))?;
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value);
+
+            Ok((name.trim().to_string(), Some(value.to_string())))
+        })
+        .collect()
+}
+
+fn required(name: &str, value: Option<String>) -> Result<String> {
+    value.ok_or_else(|| Error::config(format!("option `{name}` needs a value")))
+}
+
+fn mode(name: &str, value: Option<String>, mode: Mode) -> Result<Mode> {
+    match value {
+        None => Ok(mode),
+        Some(value) => Err(Error::config(format!(
+            "option `{name}` takes no value, got `{value}`"
+        ))),
+    }
+}
+
+fn boolean(name: &str, value: Option<String>) -> Result<bool> {
+    match value.as_deref() {
+        None | Some("true" | "yes") => Ok(true),
+        Some("false" | "no") => Ok(false),
+        Some(value) => Err(Error::config(format!(
+            "option `{name}` expects `true` or `false`, got `{value}`"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use stsr::log::Level;
+
+    use super::{LogTarget, Mode, Options, config_file, flags};
+
+    fn parse(args: &[&str]) -> stsr::Result<Options> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn defaults_without_arguments() {
+        assert_eq!(parse(&[]).unwrap(), Options::default());
+    }
+
+    #[test]
+    fn parses_values_and_switches() {
+        let options = parse(&[
+            "--separator",
+            " | ",
+            "--write-interval=500ms",
+            "--stdout",
+            "--no-write-on-changes",
+            "--control-socket",
+            "",
+            "--log-level",
+            "debug",
+            "--log-file=/tmp/stsr.log",
+            "--log-to",
+            "journald",
+            "--once",
+        ])
+        .unwrap();
+
+        assert_eq!(options.separator, " | ");
+        assert_eq!(options.write_interval, Duration::from_millis(500));
+        assert!(options.write_to_stdout);
+        assert!(!options.write_on_changes);
+        assert_eq!(options.control_socket, None);
+        assert_eq!(options.log_level, Level::Debug);
+        assert_eq!(options.log_target, LogTarget::Journald);
+        assert_eq!(options.mode, Mode::Once);
+    }
+
+    #[test]
+    fn errors_on_invalid_arguments() {
+        for args in [
+            &["--unknown"][..],
+            &["--separator"],
+            &["--write-interval", "0s"],
+            &["--stdout=maybe"],
+            &["--log-level", "verbose"],
+            &["--log-to", "file"],
+            &["once"],
+            &["--once=false"],
+            &["--no-once"],
+            &["--help=yes"],
+            &["--no-separator"],
+            &["--no-control-socket"],
+        ] {
+            assert!(parse(args).is_err(), "{args:?}");
+        }
+    }
+
+    #[test]
+    fn parses_config_file() {
+        let contents = "# bar\nseparator = \" | \"\n\nstdout-only = true\n";
+        assert_eq!(
+            config_file(contents).unwrap(),
+            [
+                (String::from("separator"), Some(String::from(" | "))),
+                (String::from("stdout-only"), Some(String::from("true"))),
+            ]
+        );
+        assert!(config_file("separator").is_err());
+    }
+
+    #[test]
+    fn command_line_overrides_config_file() {
+        let mut options = Options::default();
+        options
+            .apply(config_file("stdout = true").unwrap(), false)
+            .unwrap();
+        options
+            .apply(flags([String::from("--no-stdout")]).unwrap(), true)
+            .unwrap();
+        assert!(!options.write_to_stdout);
+        options
+            .apply(flags([String::from("--no-stdout-only")]).unwrap(), true)
+            .unwrap();
+        assert!(!options.stdout_only);
+
+        assert!(
+            Options::default()
+                .apply(config_file("once = true").unwrap(), false)
+                .is_err()
+        );
+    }
+}
@@ -1,94 +1,174 @@
 use chrono_tz::Europe::Vienna;
 
-use crate::{
-    error::{Error, Result},
-    status::{Status, sources::Source},
+use stsr::{
+    error::{ErrorKind, Result},
+    status::{
+        ErrorPolicy, HideWhen, Retry, Staleness, Status, duration,
+        schedule::{Align, Cron, Schedule},
+        smoothing::Smoothing,
+        sources::BuiltIn,
+        width::{Alignment, Overflow, Width},
+    },
 };
 
-fn status_definitions() -> Vec<Status> {
-    vec![
-        Status {
-            source: Source::cpu(),
-            format: " {}%",
-            default: "0",
-            interval: 1,
-        },
-        Status {
-            source: Source::Ram,
-            format: " {}%",
-            default: "0",
-            interval: 2,
-        },
-        Status {
-            source: Source::Battery { name: "BAT0" },
-            format: " {}%",
-            default: "0",
-            interval: 60,
-        },
-        Status {
-            source: Source::Shell {
+fn status_definitions() -> Result<Vec<Status>> {
+    Ok(vec![
+        Status::new(
+            BuiltIn::active_window(50),
+            Schedule::Interval(duration("1m")?),
+        )
+        .with_name("window")
+        .with_width(Width {
+            min: 0,
+            max: Some(40),
+            alignment: Alignment::Left,
+            overflow: Overflow::Ellipsis,
+        })
+        .with_hide_when(&[HideWhen::Empty]),
+        Status::new(BuiltIn::cpu(), Schedule::Interval(duration("1s")?))
+            .with_name("cpu")
+            .with_format(" {}% {|spark:100}")
+            .with_history(30)
+            .with_smoothing(Smoothing::Ema(0.5))
+            .with_default("0")
+            .with_width(Width {
+                min: 3,
+                max: None,
+                alignment: Alignment::Right,
+                overflow: Overflow::Cut,
+            })
+            .with_error_policy(ErrorPolicy::KeepLast),
+        Status::new(BuiltIn::Ram, Schedule::Interval(duration("2s")?))
+            .with_name("ram")
+            .with_format(" {}%")
+            .with_default("0"),
+        Status::new(BuiltIn::keyboard(), Schedule::Interval(duration("1m")?))
+            .with_name("keyboard")
+            .with_format(" {}")
+            .with_default("..."),
+        Status::new(
+            BuiltIn::Battery { name: "BAT0" },
+            Schedule::Interval(duration("1m")?),
+        )
+        .with_name("battery")
+        .with_format(" {}%")
+        .with_default("0")
+        .with_error_format(|err| match err.kind() {
+            // No battery, e.g. on a desktop.
+            ErrorKind::Io => String::new(),
+            _ => String::from("err"),
+        })
+        // Full, most likely on AC.
+        .with_hide_when(&[HideWhen::Above(98.0)]),
+        Status::new(
+            BuiltIn::volume(0, "Master", "muted"),
+            Schedule::Interval(duration("1m")?),
+        )
+        .with_name("volume")
+        .with_format(" {}")
+        .with_default("..."),
+        Status::new(
+            BuiltIn::mpd("127.0.0.1:6600", "{artist} - {title} {elapsed}"),
+            Schedule::Interval(duration("1s")?),
+        )
+        .with_name("mpd")
+        .with_format(" {}")
+        .with_default("...")
+        .with_width(Width {
+            min: 0,
+            max: Some(40),
+            alignment: Alignment::Left,
+            overflow: Overflow::Marquee,
+        })
+        .with_error_policy(ErrorPolicy::Hide)
+        .with_hide_when(&[HideWhen::Empty]),
+        Status::new(
+            BuiltIn::Shell {
                 script: r#"
                     set -e
                     host="$(hostname)"
                     disk="$(df -h / | awk 'NR==2 {print $5}')"
                     printf '%s %s' "$host" "$disk"
                 "#,
-                timeout: 2,
+                timeout: duration("2s")?,
             },
-            format: " {}",
-            default: "...",
-            interval: 30,
-        },
-        Status {
-            source: Source::Command {
+            Schedule::Interval(duration("30s")?),
+        )
+        .with_name("system")
+        .with_format(" {}")
+        .with_default("...")
+        .with_error_format(|err| match err.kind() {
+            ErrorKind::CommandTimeout => String::from("⌛"),
+            _ => String::from("err"),
+        }),
+        Status::new(
+            BuiltIn::Command {
                 cmd: "curl",
                 args: &["-fsS", "wttr.in?format=%c%t"],
-                timeout: 120,
+                timeout: duration("2m")?,
             },
-            format: "",
-            default: "...",
-            interval: 600,
-        },
-        Status {
-            source: Source::DateTime {
+            Schedule::Cron(Cron::parse("0,30 * * * *", Vienna)?),
+        )
+        .with_name("weather")
+        .with_default("...")
+        .with_retry(Retry {
+            max_attempts: 5,
+            initial_delay: duration("10s")?,
+            multiplier: 2.0,
+            max_delay: duration("5m")?,
+        })
+        .with_error_policy(ErrorPolicy::Stale {
+            after: Staleness::Age(duration("2h")?),
+            on_stale: "{}?",
+        }),
+        Status::new(
+            BuiltIn::DateTime {
                 format: "%d/%m %a",
                 timezone: Vienna,
             },
-            format: " {}",
-            default: "...",
-            interval: 1,
-        },
-        Status {
-            source: Source::DateTime {
+            Schedule::Aligned(Align::Auto),
+        )
+        .with_name("date")
+        .with_format(" {}")
+        .with_default("..."),
+        Status::new(
+            BuiltIn::DateTime {
                 format: "%H:%M",
                 timezone: Vienna,
             },
-            format: " {}",
-            default: "...",
-            interval: 1,
-        },
-    ]
+            Schedule::Aligned(Align::Auto),
+        )
+        .with_name("time")
+        .with_format(" {}")
+        .with_default("..."),
+    ])
 }
 
+/// The configured statuses, validated by `Bar::new` or `status::validate`.
 pub fn statuses() -> Result<Vec<Status>> {
-    let statuses = status_definitions();
+    status_definitions()
+}
 
-    if let Some(status) = statuses.iter().find(|status| status.interval == 0) {
-        return Err(Error::config(format!(
-            "status `interval` cannot be `0`: {status:?}"
-        )));
-    }
+#[cfg(test)]
+mod tests {
+    use stsr::status::sources::BuiltIn;
 
-    if let Some(status) = statuses.iter().find(|status| {
-        matches!(
-            &status.source,
-            Source::Command { timeout: 0, .. } | Source::Shell { timeout: 0, .. }
-        )
-    }) {
-        return Err(Error::config(format!(
-            "status `timeout` cannot be `0`: {status:?}"
-        )));
-    }
+    use super::status_definitions;
+
+    #[tokio::test]
+    async fn hides_missing_battery() {
+        let mut battery = status_definitions()
+            .unwrap()
+            .into_iter()
+            .find(|status| status.name == Some("battery"))
+            .unwrap();
+        // Fails with an `Io` error like on a desktop.
+        battery.source = Box::new(BuiltIn::Battery {
+            name: "stsr-missing",
+        });
 
-    Ok(statuses)
+        let run = battery.run_once("{}").await;
+        assert!(run.result.is_err());
+        assert_eq!(run.shown, "");
+    }
 }
@@ -1,4 +1,4 @@
-use std::{error, fmt};
+use std::{error, fmt, time::Duration};
 
 #[derive(Clone)]
 pub enum Error {
@@ -17,7 +17,7 @@ pub enum Error {
     },
     CommandTimeout {
         command: String,
-        timeout: u64,
+        timeout: Duration,
     },
     Utf8Decode {
         context: String,
@@ -35,7 +35,33 @@ pub enum Error {
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Class of an `Error`, without its details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Io,
+    Parse,
+    CommandFailed,
+    CommandTimeout,
+    Utf8Decode,
+    Calculation,
+    Config,
+    X11,
+}
+
 impl Error {
+    pub const fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Io { .. } => ErrorKind::Io,
+            Error::Parse { .. } => ErrorKind::Parse,
+            Error::CommandFailed { .. } => ErrorKind::CommandFailed,
+            Error::CommandTimeout { .. } => ErrorKind::CommandTimeout,
+            Error::Utf8Decode { .. } => ErrorKind::Utf8Decode,
+            Error::Calculation { .. } => ErrorKind::Calculation,
+            Error::Config { .. } => ErrorKind::Config,
+            Error::X11 { .. } => ErrorKind::X11,
+        }
+    }
+
     /// Create an I/O error with file path context
     pub fn io(path: impl Into<String>, message: impl fmt::Display) -> Self {
         Error::Io {
@@ -88,11 +114,8 @@ impl fmt::Debug for Error {
             } => {
                 write!(f, "`{}` failed with status {}: {}", command, status, stderr)
             }
-            Error::CommandTimeout {
-                command,
-                timeout: timeout_secs,
-            } => {
-                write!(f, "`{}` timed out after {}s", command, timeout_secs)
+            Error::CommandTimeout { command, timeout } => {
+                write!(f, "`{}` timed out after {:?}", command, timeout)
             }
             Error::Utf8Decode { context } => write!(f, "utf-8 decode: {}", context),
             Error::Calculation { message } => write!(f, "calculation: {}", message),
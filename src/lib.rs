@@ -0,0 +1,15 @@
+//! Status bar for X11 that writes to the root window name, e.g. for dwm.
+//!
+//! A `Bar` runs `Status`es, each fetching its value from a `Source`: one of the `BuiltIn`
+//! sources or a custom implementation of the trait.
+
+pub mod error;
+pub mod log;
+pub mod status;
+pub mod x11;
+
+pub use error::{Error, Result};
+pub use status::{
+    Bar, Status, duration,
+    sources::{BuiltIn, Source},
+};
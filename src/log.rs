@@ -0,0 +1,456 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+
+pub use journal::{JOURNAL_SOCKET, Journal};
+pub use syslog::{SYSLOG_SOCKET, Syslog};
+
+use crate::error::{Error, ErrorKind, Result};
+
+mod journal;
+mod syslog;
+
+static LOGGER: LazyLock<Mutex<Logger>> = LazyLock::new(|| Mutex::new(Logger::new(Level::Info)));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "ERROR",
+            Self::Warn => "WARN",
+            Self::Info => "INFO",
+            Self::Debug => "DEBUG",
+            Self::Trace => "TRACE",
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = Error;
+
+    fn from_str(level: &str) -> Result<Self> {
+        match level.to_ascii_lowercase().as_str() {
+            "error" => Ok(Self::Error),
+            "warn" => Ok(Self::Warn),
+            "info" => Ok(Self::Info),
+            "debug" => Ok(Self::Debug),
+            "trace" => Ok(Self::Trace),
+            _ => Err(Error::config(format!(
+                "invalid log level `{level}`, expected `error`, `warn`, `info`, `debug` or `trace`"
+            ))),
+        }
+    }
+}
+
+/// One log message.
+#[derive(Debug, Clone, Copy)]
+pub struct Record<'a> {
+    pub level: Level,
+    /// Name of the status the message is about.
+    pub status: Option<&'a str>,
+    /// Class of the error the message reports.
+    pub kind: Option<ErrorKind>,
+    pub message: &'a str,
+}
+
+/// Where log lines are written.
+#[derive(Debug)]
+pub enum Output {
+    Stderr,
+    File(LogFile),
+    /// The systemd journal, for sessions whose stderr is lost, e.g. started from `.xinitrc`.
+    Journal(Journal),
+    Syslog(Syslog),
+}
+
+impl Output {
+    fn path(&self) -> &Path {
+        match self {
+            Self::Stderr => Path::new("stderr"),
+            Self::File(file) => &file.path,
+            Self::Journal(journal) => journal.path(),
+            Self::Syslog(syslog) => syslog.path(),
+        }
+    }
+}
+
+/// Log file renamed to `<path>.1` once it would grow beyond `max_size` bytes; older files move
+/// on to `<path>.2` and so on, up to `backups` files.
+#[derive(Debug)]
+pub struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    backups: usize,
+}
+
+impl LogFile {
+    pub fn open(path: impl Into<PathBuf>, max_size: u64, backups: usize) -> Result<Self> {
+        let path = path.into();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|err| Error::io(path.display().to_string(), err))?;
+        let size = file
+            .metadata()
+            .map_err(|err| Error::io(path.display().to_string(), err))?
+            .len();
+
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_size,
+            backups,
+        })
+    }
+
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        let length = line.len() as u64 + 1;
+        if self.size > 0 && self.size + length > self.max_size {
+            self.rotate()?;
+        }
+
+        writeln!(self.file, "{line}")?;
+        self.size += length;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for index in (1..self.backups).rev() {
+            // Missing backups are fine, e.g. after the first rotations.
+            let _ = fs::rename(backup(&self.path, index), backup(&self.path, index + 1));
+        }
+        if self.backups > 0 {
+            fs::rename(&self.path, backup(&self.path, 1))?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn backup(path: &Path, index: usize) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".{index}"));
+    PathBuf::from(backup)
+}
+
+/// Allows `burst` errors and warnings per status and level in every `window`, so a failing
+/// source logs a few lines per window instead of one per run.
+#[derive(Debug)]
+struct RateLimit {
+    burst: u32,
+    window: Duration,
+    windows: HashMap<(Option<String>, Level), Window>,
+}
+
+#[derive(Debug)]
+struct Window {
+    started: Instant,
+    lines: u32,
+    suppressed: u32,
+}
+
+impl RateLimit {
+    /// `None` if the record is suppressed, otherwise the number of records suppressed before it.
+    fn check(&mut self, record: &Record, now: Instant) -> Option<u32> {
+        if record.level > Level::Warn {
+            return Some(0);
+        }
+
+        let window = self
+            .windows
+            .entry((record.status.map(String::from), record.level))
+            .or_insert(Window {
+                started: now,
+                lines: 0,
+                suppressed: 0,
+            });
+
+        if now.duration_since(window.started) >= self.window {
+            window.started = now;
+            window.lines = 0;
+        }
+        if window.lines >= self.burst {
+            window.suppressed += 1;
+            return None;
+        }
+
+        window.lines += 1;
+        Some(std::mem::take(&mut window.suppressed))
+    }
+}
+
+#[derive(Debug)]
+pub struct Logger {
+    level: Level,
+    output: Output,
+    rate_limit: RateLimit,
+}
+
+impl Logger {
+    /// Logs `level` and more severe records to stderr, at most 5 errors and warnings per status
+    /// and minute.
+    pub fn new(level: Level) -> Self {
+        Self {
+            level,
+            output: Output::Stderr,
+            rate_limit: RateLimit {
+                burst: 5,
+                window: Duration::from_secs(60),
+                windows: HashMap::new(),
+            },
+        }
+    }
+
+    pub fn with_output(mut self, output: Output) -> Self {
+        self.output = output;
+        self
+    }
+
+    pub fn with_rate_limit(mut self, burst: u32, window: Duration) -> Self {
+        self.rate_limit.burst = burst;
+        self.rate_limit.window = window;
+        self
+    }
+
+    fn log(&mut self, record: &Record) {
+        if record.level > self.level {
+            return;
+        }
+        let Some(suppressed) = self.rate_limit.check(record, Instant::now()) else {
+            return;
+        };
+
+        let message = message(record, suppressed);
+        let line = || format_line(Local::now(), record, &message);
+        let written = match &mut self.output {
+            Output::Stderr => {
+                eprintln!("{}", line());
+                Ok(())
+            }
+            Output::File(file) => file.write(&line()),
+            Output::Journal(journal) => journal.send(record, &message),
+            Output::Syslog(syslog) => syslog.send(record, &message),
+        };
+
+        if let Err(err) = written {
+            eprintln!("{}", line());
+            eprintln!(
+                "writing log to `{}` failed: {err}",
+                self.output.path().display()
+            );
+        }
+    }
+}
+
+/// The record message, noting the records suppressed before it.
+fn message(record: &Record, suppressed: u32) -> String {
+    if suppressed > 0 {
+        format!("{} ({suppressed} similar lines suppressed)", record.message)
+    } else {
+        record.message.to_string()
+    }
+}
+
+/// `2026-01-31T18:00:00.000 WARN  [cpu] message`.
+fn format_line(time: DateTime<Local>, record: &Record, message: &str) -> String {
+    let mut line = format!(
+        "{} {:<5} ",
+        time.format("%Y-%m-%dT%H:%M:%S%.3f"),
+        record.level.as_str()
+    );
+    if let Some(status) = record.status {
+        line.push_str(&format!("[{status}] "));
+    }
+    line.push_str(message);
+
+    line
+}
+
+fn global() -> std::sync::MutexGuard<'static, Logger> {
+    // A panic while logging leaves the logger usable.
+    LOGGER.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Replaces the default logger, which logs `Info` and more severe records to stderr.
+pub fn init(logger: Logger) {
+    *global() = logger;
+}
+
+pub fn enabled(level: Level) -> bool {
+    level <= global().level
+}
+
+pub fn log(
+    level: Level,
+    status: Option<&str>,
+    kind: Option<ErrorKind>,
+    message: impl fmt::Display,
+) {
+    let mut logger = global();
+    if level > logger.level {
+        return;
+    }
+
+    logger.log(&Record {
+        level,
+        status,
+        kind,
+        message: &message.to_string(),
+    });
+}
+
+/// Logs a failed run of `status`.
+pub fn status_error(status: &str, err: &Error) {
+    log(Level::Error, Some(status), Some(err.kind()), err);
+}
+
+pub fn error(status: Option<&str>, message: impl fmt::Display) {
+    log(Level::Error, status, None, message);
+}
+
+pub fn warn(status: Option<&str>, message: impl fmt::Display) {
+    log(Level::Warn, status, None, message);
+}
+
+pub fn info(status: Option<&str>, message: impl fmt::Display) {
+    log(Level::Info, status, None, message);
+}
+
+pub fn debug(status: Option<&str>, message: impl fmt::Display) {
+    log(Level::Debug, status, None, message);
+}
+
+pub fn trace(status: Option<&str>, message: impl fmt::Display) {
+    log(Level::Trace, status, None, message);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        fs,
+        os::unix::net::UnixDatagram,
+        path::Path,
+        time::{Duration, Instant},
+    };
+
+    use chrono::{Local, TimeZone};
+
+    use super::{Level, LogFile, RateLimit, Record, backup, format_line, message};
+
+    /// Datagram that `send` writes to a fresh socket standing in for journald or syslog.
+    pub(super) fn receive(name: &str, send: impl FnOnce(&Path)) -> String {
+        let path = std::env::temp_dir().join(format!("stsr-{name}-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let server = UnixDatagram::bind(&path).unwrap();
+
+        send(&path);
+
+        let mut buffer = [0; 256];
+        let length = server.recv(&mut buffer).unwrap();
+        fs::remove_file(path).unwrap();
+        String::from_utf8_lossy(&buffer[..length]).into_owned()
+    }
+
+    fn record(level: Level, status: Option<&'static str>) -> Record<'static> {
+        Record {
+            level,
+            status,
+            kind: None,
+            message: "failed",
+        }
+    }
+
+    #[test]
+    fn parses_levels() {
+        assert_eq!("warn".parse::<Level>().unwrap(), Level::Warn);
+        assert_eq!("DEBUG".parse::<Level>().unwrap(), Level::Debug);
+        assert!("verbose".parse::<Level>().is_err());
+        assert!(Level::Error < Level::Trace);
+    }
+
+    #[test]
+    fn formats_lines() {
+        let time = Local.with_ymd_and_hms(2026, 1, 31, 18, 0, 0).unwrap();
+
+        assert_eq!(
+            format_line(time, &record(Level::Warn, Some("cpu")), "failed"),
+            "2026-01-31T18:00:00.000 WARN  [cpu] failed"
+        );
+        assert_eq!(
+            format_line(
+                time,
+                &record(Level::Error, None),
+                &message(&record(Level::Error, None), 3)
+            ),
+            "2026-01-31T18:00:00.000 ERROR failed (3 similar lines suppressed)"
+        );
+    }
+
+    #[test]
+    fn rate_limits_errors_per_status() {
+        let mut limit = RateLimit {
+            burst: 2,
+            window: Duration::from_secs(60),
+            windows: HashMap::new(),
+        };
+        let start = Instant::now();
+        let mut check = |level, status, after| {
+            limit.check(&record(level, status), start + Duration::from_secs(after))
+        };
+
+        assert_eq!(check(Level::Error, Some("cpu"), 0), Some(0));
+        assert_eq!(check(Level::Error, Some("cpu"), 1), Some(0));
+        assert_eq!(check(Level::Error, Some("cpu"), 2), None);
+        assert_eq!(check(Level::Error, Some("cpu"), 3), None);
+        assert_eq!(check(Level::Error, Some("ram"), 4), Some(0));
+        assert_eq!(check(Level::Debug, Some("cpu"), 5), Some(0));
+
+        assert_eq!(check(Level::Error, Some("cpu"), 60), Some(2));
+    }
+
+    #[test]
+    fn rotates_log_files() {
+        let path = std::env::temp_dir().join(format!("stsr-log-{}", std::process::id()));
+        let mut file = LogFile::open(&path, 10, 2).unwrap();
+
+        for line in ["first", "second", "third", "fourth"] {
+            file.write(line).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(fs::read_to_string(backup(&path, 1)).unwrap(), "third\n");
+        assert_eq!(fs::read_to_string(backup(&path, 2)).unwrap(), "second\n");
+
+        for path in [path.clone(), backup(&path, 1), backup(&path, 2)] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::{
+    io,
+    os::unix::net::UnixDatagram,
+    path::{Path, PathBuf},
+};
+
+use super::{Level, Record};
+use crate::error::{Error, Result};
+
+/// Socket of the systemd journal native protocol.
+pub const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Sends records to the systemd journal, with the status and error kind as `STSR_STATUS` and
+/// `STSR_ERROR_KIND` fields.
+#[derive(Debug)]
+pub struct Journal {
+    socket: UnixDatagram,
+    path: PathBuf,
+}
+
+impl Journal {
+    /// Sends to the journal socket at `path`, usually `JOURNAL_SOCKET`.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if !path.exists() {
+            return Err(Error::io(
+                path.display().to_string(),
+                "journal socket not found",
+            ));
+        }
+        let socket =
+            UnixDatagram::unbound().map_err(|err| Error::io(path.display().to_string(), err))?;
+
+        Ok(Self { socket, path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(super) fn send(&self, record: &Record, message: &str) -> io::Result<()> {
+        self.socket.send_to(&entry(record, message), &self.path)?;
+        Ok(())
+    }
+}
+
+/// Syslog severity of `level`, as used by the journal `PRIORITY` field.
+pub(super) const fn priority(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+/// Journal entry in the native protocol: `KEY=value` lines, or for values containing newlines
+/// the key, a newline, the value length as little-endian `u64`, the value and a newline.
+fn entry(record: &Record, message: &str) -> Vec<u8> {
+    let mut entry = Vec::new();
+    let mut field = |key: &str, value: &str| {
+        entry.extend_from_slice(key.as_bytes());
+        if value.contains('\n') {
+            entry.push(b'\n');
+            entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            entry.push(b'=');
+        }
+        entry.extend_from_slice(value.as_bytes());
+        entry.push(b'\n');
+    };
+
+    field("MESSAGE", message);
+    field("PRIORITY", &priority(record.level).to_string());
+    field("SYSLOG_IDENTIFIER", "stsr");
+    if let Some(status) = record.status {
+        field("STSR_STATUS", status);
+    }
+    if let Some(kind) = record.kind {
+        field("STSR_ERROR_KIND", &format!("{kind:?}"));
+    }
+
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Journal, entry};
+    use crate::{
+        error::ErrorKind,
+        log::{Level, Record, tests::receive},
+    };
+
+    fn record(message: &'static str) -> Record<'static> {
+        Record {
+            level: Level::Error,
+            status: Some("weather"),
+            kind: Some(ErrorKind::CommandTimeout),
+            message,
+        }
+    }
+
+    #[test]
+    fn encodes_fields() {
+        assert_eq!(
+            String::from_utf8(entry(&record("timed out"), "timed out")).unwrap(),
+            "MESSAGE=timed out\nPRIORITY=3\nSYSLOG_IDENTIFIER=stsr\n\
+             STSR_STATUS=weather\nSTSR_ERROR_KIND=CommandTimeout\n"
+        );
+    }
+
+    #[test]
+    fn encodes_multiline_values_with_length() {
+        let entry = entry(&record("a\nb"), "a\nb");
+        assert!(entry.starts_with(b"MESSAGE\n\x03\0\0\0\0\0\0\0a\nb\nPRIORITY=3\n"));
+    }
+
+    #[test]
+    fn sends_to_socket() {
+        let entry = receive("journal", |path| {
+            Journal::open(path)
+                .unwrap()
+                .send(&record("timed out"), "timed out")
+                .unwrap()
+        });
+        assert!(entry.contains("STSR_STATUS=weather\n"), "{entry}");
+    }
+}
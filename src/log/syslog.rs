@@ -0,0 +1,106 @@
+use std::{
+    io,
+    os::unix::net::UnixDatagram,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+
+use super::{Record, journal::priority};
+use crate::error::{Error, Result};
+
+/// Socket of the local syslog daemon.
+pub const SYSLOG_SOCKET: &str = "/dev/log";
+
+/// Facility of user-level messages.
+const USER: u8 = 1;
+
+/// Sends records to syslog in the RFC 3164 format, e.g.
+/// `<11>Jan 31 18:00:00 stsr[42]: [weather] timed out`.
+#[derive(Debug)]
+pub struct Syslog {
+    socket: UnixDatagram,
+    path: PathBuf,
+}
+
+impl Syslog {
+    /// Sends to the syslog socket at `path`, usually `SYSLOG_SOCKET`.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if !path.exists() {
+            return Err(Error::io(
+                path.display().to_string(),
+                "syslog socket not found",
+            ));
+        }
+        let socket =
+            UnixDatagram::unbound().map_err(|err| Error::io(path.display().to_string(), err))?;
+
+        Ok(Self { socket, path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(super) fn send(&self, record: &Record, message: &str) -> io::Result<()> {
+        let line = format_message(Local::now(), std::process::id(), record, message);
+        self.socket.send_to(line.as_bytes(), &self.path)?;
+        Ok(())
+    }
+}
+
+fn format_message(time: DateTime<Local>, pid: u32, record: &Record, message: &str) -> String {
+    let status = record
+        .status
+        .map_or_else(String::new, |status| format!("[{status}] "));
+
+    format!(
+        "<{}>{} stsr[{pid}]: {status}{message}",
+        USER * 8 + priority(record.level),
+        time.format("%b %e %H:%M:%S"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, TimeZone};
+
+    use super::{Syslog, format_message};
+    use crate::log::{Level, Record, tests::receive};
+
+    fn record(status: Option<&'static str>) -> Record<'static> {
+        Record {
+            level: Level::Warn,
+            status,
+            kind: None,
+            message: "no battery",
+        }
+    }
+
+    #[test]
+    fn formats_messages() {
+        let time = Local.with_ymd_and_hms(2026, 1, 5, 18, 0, 0).unwrap();
+
+        assert_eq!(
+            format_message(time, 42, &record(Some("battery")), "no battery"),
+            "<12>Jan  5 18:00:00 stsr[42]: [battery] no battery"
+        );
+        assert_eq!(
+            format_message(time, 42, &record(None), "no battery"),
+            "<12>Jan  5 18:00:00 stsr[42]: no battery"
+        );
+    }
+
+    #[test]
+    fn sends_to_socket() {
+        let message = receive("syslog", |path| {
+            Syslog::open(path)
+                .unwrap()
+                .send(&record(Some("battery")), "no battery")
+                .unwrap()
+        });
+        assert!(message.starts_with("<12>"), "{message}");
+        assert!(message.ends_with("]: [battery] no battery"), "{message}");
+    }
+}
@@ -1,21 +1,87 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use cli::{LogTarget, Mode, Options};
+use stsr::{
+    Bar, Result,
+    log::{self, JOURNAL_SOCKET, Journal, LogFile, Logger, Output, SYSLOG_SOCKET, Syslog},
+    status::{self, IdlePolicy},
+    x11::X11rb,
+};
+
+mod cli;
 mod config;
-mod error;
-mod status;
-mod x11;
 
 #[tokio::main(flavor = "current_thread")]
-async fn main() -> error::Result<()> {
+async fn main() -> Result<()> {
+    let options = Options::parse(std::env::args().skip(1))?;
+    if options.mode == Mode::Help {
+        print!("{}", cli::USAGE);
+        return Ok(());
+    }
+
+    let output = match &options.log_target {
+        LogTarget::Stderr => Output::Stderr,
+        LogTarget::File(path) => Output::File(LogFile::open(path, 1024 * 1024, 3)?),
+        LogTarget::Journald => Output::Journal(Journal::open(JOURNAL_SOCKET)?),
+        LogTarget::Syslog => Output::Syslog(Syslog::open(SYSLOG_SOCKET)?),
+    };
+    log::init(Logger::new(options.log_level).with_output(output));
+
     let statuses = config::statuses()?;
-    let x11rb = x11::X11rb::new(5)?;
 
-    let mut bar = status::Bar::new(statuses, x11rb)
+    match options.mode {
+        Mode::Check => {
+            status::validate(&statuses)?;
+            println!("configuration ok: {} statuses", statuses.len());
+            return Ok(());
+        }
+        Mode::ListSources => {
+            for status in &statuses {
+                println!(
+                    "{}\t{}\t{:?}",
+                    status.name.unwrap_or("-"),
+                    status.source.label(),
+                    status.schedule
+                );
+            }
+            return Ok(());
+        }
+        Mode::Run | Mode::Once | Mode::Help => {}
+    }
+
+    let mut bar = Bar::new(statuses)?
         .with_replace_marker("{}")
-        .with_separator(" ")
-        .with_write_interval(Duration::from_secs(1))
-        .with_write_to_stdout(false)
-        .with_write_on_changes(true);
+        .with_separator(&options.separator)
+        .with_write_interval(options.write_interval)
+        .with_write_to_stdout(options.write_to_stdout || options.stdout_only)
+        .with_write_on_changes(options.write_on_changes)
+        .with_idle_policy(IdlePolicy::Stretch(10))
+        .with_idle_after(Duration::from_secs(300));
+
+    if options.mode == Mode::Once {
+        let started = Instant::now();
+        let (output, runs) = bar.once().await;
+        let failed = runs.iter().filter(|run| run.result.is_err()).count();
+
+        // The bar alone on stdout, so scripts can use it.
+        for run in &runs {
+            eprintln!("{run}");
+        }
+        eprintln!(
+            "{} statuses in {:.1?}, {failed} failed",
+            runs.len(),
+            started.elapsed()
+        );
+        println!("{output}");
+        return Ok(());
+    }
+
+    if !options.stdout_only {
+        bar = bar.with_x11(X11rb::new(5)?);
+    }
+    if let Some(path) = options.control_socket {
+        bar = bar.with_control_socket(path);
+    }
 
     bar.run().await;
 
@@ -1,66 +1,739 @@
-use std::cell::RefCell;
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    path::PathBuf,
+};
 
 use futures::future::join_all;
 use tokio::{
+    net::UnixListener,
     signal,
-    time::{Duration, MissedTickBehavior},
+    sync::{Notify, watch},
+    time::{Duration, Instant, MissedTickBehavior},
 };
 
+pub use schedule::duration;
+
+use history::History;
+use schedule::{Align, Schedule, Ticker};
+use smoothing::{Smoother, Smoothing};
+use units::apply_filters;
+use utils::fill_filtered;
+use width::{Marquee, Width};
+
 use crate::{
     error::{Error, Result},
+    log::{self, Level},
     x11::X11rb,
 };
 
+pub mod control;
+pub mod history;
+pub mod schedule;
+pub mod smoothing;
 pub mod sources;
+mod units;
 mod utils;
+pub mod width;
+
+/// How statuses are scheduled while the screen is idle or blanked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdlePolicy {
+    /// Keep running at the normal interval.
+    Ignore,
+    /// Skip all interval runs until the screen wakes up.
+    Pause,
+    /// Run only on every n-th interval tick.
+    Stretch(u32),
+}
+
+impl IdlePolicy {
+    /// Policy applied to a status with `schedule`; wall-clock schedules keep running, since a
+    /// slowed down clock would be wrong while the screen is still on.
+    fn for_schedule(self, schedule: &Schedule) -> Self {
+        match schedule {
+            Schedule::Interval(_) => self,
+            Schedule::Aligned(_) | Schedule::Cron(_) => Self::Ignore,
+        }
+    }
+}
+
+/// Re-runs a failed source sooner than its schedule, with exponential backoff.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Retry {
+    /// Retries after consecutive failures; afterwards only the schedule runs the source.
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    /// Factor applied to the delay after each failed retry.
+    pub multiplier: f64,
+    pub max_delay: Duration,
+}
+
+impl Retry {
+    /// Delay before retry number `attempt`, starting at `0`.
+    fn delay(&self, attempt: u32) -> Duration {
+        let delay = self.initial_delay.as_secs_f64()
+            * self.multiplier.powi(attempt.try_into().unwrap_or(i32::MAX));
+
+        // Invalid settings, e.g. a negative multiplier, fall back to `max_delay`.
+        Duration::try_from_secs_f64(delay.min(self.max_delay.as_secs_f64()))
+            .unwrap_or(self.max_delay)
+    }
+}
+
+/// What a status shows when its source fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Show the `error_format` of the error, passed through `format`.
+    Show,
+    /// Keep the last successful output.
+    KeepLast,
+    /// Keep the last successful output, wrapped in `on_stale` once it is stale, e.g. `{} ?`.
+    Stale {
+        after: Staleness,
+        on_stale: &'static str,
+    },
+    /// Show nothing.
+    Hide,
+}
+
+/// When kept output counts as stale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Staleness {
+    /// After this many consecutive failed runs.
+    Failures(u32),
+    /// After this long without a successful run.
+    Age(Duration),
+}
+
+/// Rule hiding a status, together with its separator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HideWhen {
+    /// The source output is empty, instead of showing `default`.
+    Empty,
+    /// The source output equals this text.
+    Equals(&'static str),
+    /// The source output is a number below this value.
+    Below(f64),
+    /// The source output is a number above this value.
+    Above(f64),
+    /// The source failed, regardless of the `ErrorPolicy`.
+    Error,
+}
+
+impl HideWhen {
+    /// Whether the successful source `output` is hidden.
+    fn hides(&self, output: &str) -> bool {
+        let number = || output.trim().parse::<f64>().ok();
+
+        match *self {
+            Self::Empty => output.is_empty(),
+            Self::Equals(value) => output == value,
+            Self::Below(limit) => number().is_some_and(|number| number < limit),
+            Self::Above(limit) => number().is_some_and(|number| number > limit),
+            Self::Error => false,
+        }
+    }
+}
+
+/// Text shown for a failed run, e.g. `|err| "⌛".into()`; empty hides the output.
+pub type ErrorFormat = fn(&Error) -> String;
+
+fn default_error_format(_: &Error) -> String {
+    String::from("err")
+}
 
 #[derive(Debug)]
 pub struct Status {
-    pub source: sources::Source,
+    /// Identifies the status in logs and control commands; a single word, unique in the bar.
+    pub name: Option<&'static str>,
+    pub source: Box<dyn sources::Source>,
     pub format: &'static str,
     pub default: &'static str,
-    /// Minimum run interval in seconds; missed ticks are skipped for long runs.
-    pub interval: u64,
+    pub schedule: Schedule,
+    pub retry: Option<Retry>,
+    pub error_policy: ErrorPolicy,
+    pub error_format: ErrorFormat,
+    pub hide_when: &'static [HideWhen],
+    pub width: Option<Width>,
+    /// Numeric outputs rendered by `{|spark}`.
+    pub history: Option<History>,
+    pub smoother: Option<Smoother>,
 }
 
 impl Status {
+    pub fn new(source: impl sources::Source + 'static, schedule: Schedule) -> Self {
+        Self {
+            name: None,
+            source: Box::new(source),
+            format: "",
+            default: "",
+            schedule,
+            retry: None,
+            error_policy: ErrorPolicy::Show,
+            error_format: default_error_format,
+            hide_when: &[],
+            width: None,
+            history: None,
+            smoother: None,
+        }
+    }
+
+    pub const fn with_name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// The `name`, or the source label for unnamed statuses.
+    pub fn label(&self) -> String {
+        self.name
+            .map_or_else(|| self.source.label(), |name| name.to_string())
+    }
+
+    pub const fn with_format(mut self, format: &'static str) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub const fn with_default(mut self, default: &'static str) -> Self {
+        self.default = default;
+        self
+    }
+
+    pub const fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub const fn with_error_policy(mut self, error_policy: ErrorPolicy) -> Self {
+        self.error_policy = error_policy;
+        self
+    }
+
+    pub const fn with_error_format(mut self, error_format: ErrorFormat) -> Self {
+        self.error_format = error_format;
+        self
+    }
+
+    /// Hides the status if any of the rules matches.
+    pub const fn with_hide_when(mut self, hide_when: &'static [HideWhen]) -> Self {
+        self.hide_when = hide_when;
+        self
+    }
+
+    /// Pads or cuts values to a display width, keeping the block width stable.
+    pub const fn with_width(mut self, width: Width) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// Keeps the last `length` numeric outputs for `{|spark}` sparklines.
+    pub fn with_history(mut self, length: usize) -> Self {
+        self.history = Some(History::new(length));
+        self
+    }
+
+    /// Smooths numeric outputs; the unsmoothed value is available as `{raw}` in `format`.
+    pub fn with_smoothing(mut self, smoothing: Smoothing) -> Self {
+        self.smoother = Some(Smoother::new(smoothing));
+        self
+    }
+
+    /// Checks the schedule, name, retry, smoothing and source settings, returning `Error::Config`
+    /// for invalid ones.
+    pub fn validate(&self) -> Result<()> {
+        if self.schedule == Schedule::Interval(Duration::ZERO) {
+            return Err(Error::config(format!(
+                "status `interval` cannot be `0`: {self:?}"
+            )));
+        }
+
+        if self.schedule == Schedule::Aligned(Align::Auto) && self.source.clock().is_none() {
+            return Err(Error::config(format!(
+                "status `Align::Auto` requires a clock source such as `DateTime`: {self:?}"
+            )));
+        }
+
+        self.source.validate()?;
+
+        if self.name.is_some_and(|name| {
+            name.is_empty()
+                || name.contains(char::is_whitespace)
+                || name.chars().all(|char| char.is_ascii_digit())
+        }) {
+            return Err(Error::config(format!(
+                "status `name` must be a single word that is not a number: {self:?}"
+            )));
+        }
+
+        if self.retry.is_some_and(|retry| {
+            retry.initial_delay.is_zero() || !retry.multiplier.is_finite() || retry.multiplier < 1.0
+        }) {
+            return Err(Error::config(format!(
+                "status `retry` needs a non-zero `initial_delay` and a `multiplier` of at least `1`: {self:?}"
+            )));
+        }
+
+        if self
+            .smoother
+            .as_ref()
+            .is_some_and(|smoother| !smoother.smoothing().is_valid())
+        {
+            return Err(Error::config(format!(
+                "status `smoothing` needs an `Ema` weight in `(0, 1]` and a non-zero window: {self:?}"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// `value` padded or cut to the `width`.
+    fn sized(&self, value: &str) -> String {
+        match self.width {
+            Some(width) => width.apply(value),
+            None => value.to_string(),
+        }
+    }
+
+    /// Embeds `value` in `format`, at the replace marker or at `{|filter}` placeholders
+    /// with the value passed through the unit filters, e.g. `{|bytes}`; `{|spark}` and
+    /// `{|spark:max}` render the history instead, and `{raw}` the unsmoothed value.
     fn format_value(&self, value: &str, replace_marker: &str) -> String {
         if self.format.is_empty() {
-            value.to_string()
+            return self.sized(value);
+        }
+
+        self.fill_format(self.format, value, replace_marker)
+    }
+
+    /// `format_value` for `format`, a part of the status format.
+    fn fill_format(&self, format: &str, value: &str, replace_marker: &str) -> String {
+        fill_filtered(format, |name, filters| {
+            if name == "raw" {
+                let raw = self.smoother.as_ref().and_then(Smoother::raw);
+                return Some(self.sized(&apply_filters(raw.unwrap_or(value), filters)));
+            }
+            if !name.is_empty() || filters.is_empty() {
+                return None;
+            }
+
+            match filters.split_once(':').unwrap_or((filters, "")) {
+                ("spark", max) => Some(
+                    self.history
+                        .as_ref()
+                        .map_or_else(String::new, |history| history.sparkline(max.parse().ok())),
+                ),
+                _ => Some(self.sized(&apply_filters(value, filters))),
+            }
+        })
+        .replace(replace_marker, &self.sized(value))
+    }
+
+    /// Formatted `value`, scrolling within `format` if the `width` overflow is a marquee; the
+    /// format around the scrolling value is filled like `format_value`.
+    fn block(&self, value: &str, replace_marker: &str) -> Block {
+        let marquee = self.width.and_then(|width| width.marquee(value));
+        let parts = if self.format.is_empty() {
+            Some(("", ""))
         } else {
-            self.format.replace(replace_marker, value)
+            self.format.split_once(replace_marker)
+        };
+
+        match (marquee, parts) {
+            (Some(marquee), Some((before, after))) => Block {
+                before: self.fill_format(before, value, replace_marker),
+                marquee: Some(marquee),
+                after: self.fill_format(after, value, replace_marker),
+            },
+            _ => Block::from(self.format_value(value, replace_marker)),
         }
     }
 
-    fn default_output(&self, replace_marker: &str) -> String {
-        self.format_value(self.default, replace_marker)
+    fn default_output(&self, replace_marker: &str) -> Block {
+        self.block(self.default, replace_marker)
     }
 
-    pub async fn run(&mut self, shared_output: &RefCell<String>, replace_marker: &str) {
-        let mut interval = tokio::time::interval(Duration::from_secs(self.interval));
-        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    /// Output after `failures` consecutive failed runs; `None` keeps the current output.
+    fn error_output(
+        &self,
+        err: &Error,
+        last_good: Option<&Block>,
+        failures: u32,
+        since_success: Duration,
+        replace_marker: &str,
+    ) -> Option<Block> {
+        match (self.error_policy, last_good) {
+            (ErrorPolicy::Show, _) => {
+                let error = (self.error_format)(err);
+
+                Some(if error.is_empty() {
+                    Block::default()
+                } else {
+                    Block::from(self.format_value(&error, replace_marker))
+                })
+            }
+            (ErrorPolicy::Hide, _) => Some(Block::default()),
+            (ErrorPolicy::KeepLast | ErrorPolicy::Stale { .. }, None) => None,
+            (ErrorPolicy::KeepLast, Some(last_good)) => Some(last_good.clone()),
+            (ErrorPolicy::Stale { after, on_stale }, Some(last_good)) => {
+                let stale = match after {
+                    Staleness::Failures(limit) => failures >= limit,
+                    Staleness::Age(age) => since_success >= age,
+                };
+
+                // Output hidden by `hide_when` stays hidden instead of showing a bare `on_stale`.
+                Some(if stale && !last_good.is_hidden() {
+                    Block::from(on_stale.replace(replace_marker, &last_good.render()))
+                } else {
+                    last_good.clone()
+                })
+            }
+        }
+    }
+
+    /// Output of a successful run, updating the smoothing and history.
+    fn success_output(&mut self, output: String, replace_marker: &str) -> Block {
+        let output = match &mut self.smoother {
+            Some(smoother) => smoother.smooth(output),
+            None => output,
+        };
+
+        if let Some(history) = &mut self.history
+            && let Ok(sample) = output.trim().parse()
+        {
+            history.push(sample);
+        }
+
+        if self.hide_when.iter().any(|rule| rule.hides(&output)) {
+            Block::default()
+        } else if output.is_empty() {
+            self.default_output(replace_marker)
+        } else {
+            self.block(&output, replace_marker)
+        }
+    }
+
+    /// Runs the source once, timing it, with the output the first run of `run` would show.
+    pub async fn run_once(&mut self, replace_marker: &str) -> SingleRun {
+        let started = Instant::now();
+        let result = self.source.output().await;
+        let elapsed = started.elapsed();
+
+        let block = match &result {
+            Ok(output) => self.success_output(output.clone(), replace_marker),
+            Err(_) if self.hide_when.contains(&HideWhen::Error) => Block::default(),
+            Err(err) => self
+                .error_output(err, None, 1, Duration::ZERO, replace_marker)
+                .unwrap_or_else(|| self.default_output(replace_marker)),
+        };
+
+        SingleRun {
+            label: self.label(),
+            elapsed,
+            result,
+            shown: block.render(),
+        }
+    }
+
+    async fn run(
+        &mut self,
+        slot: &Slot,
+        replace_marker: &str,
+        mut idle: watch::Receiver<bool>,
+        idle_policy: IdlePolicy,
+        mut paused: watch::Receiver<bool>,
+    ) {
+        let mut ticker = Ticker::new(&self.schedule, self.source.as_ref());
+        let idle_policy = idle_policy.for_schedule(&self.schedule);
+        let mut idle_ticks = 0;
+        let mut failures = 0;
+        let mut retry_at = None;
+        // Formatted output and time of the last successful run.
+        let mut last_good = None;
+        let mut last_success = Instant::now();
 
         loop {
-            interval.tick().await;
+            tokio::select! {
+                () = ticker.tick() => {
+                    if *paused.borrow() {
+                        continue;
+                    }
+                    if *idle.borrow() {
+                        match idle_policy {
+                            IdlePolicy::Ignore => {}
+                            IdlePolicy::Pause => continue,
+                            IdlePolicy::Stretch(factor) => {
+                                idle_ticks += 1;
+                                if idle_ticks < factor {
+                                    continue;
+                                }
+                            }
+                        }
+                    }
+                    idle_ticks = 0;
+                }
+                () = retry_timer(retry_at) => {
+                    retry_at = None;
+                    // Statuses slowed down while idle are refreshed on wake up instead.
+                    if *paused.borrow() || (*idle.borrow() && idle_policy != IdlePolicy::Ignore) {
+                        continue;
+                    }
+                }
+                changed = self.source.changed() => {
+                    if let Err(err) = changed {
+                        log::log(Level::Warn, Some(&self.label()), Some(err.kind()), err);
+                    }
+                    if *paused.borrow() {
+                        continue;
+                    }
+                }
+                Ok(()) = idle.changed() => {
+                    // Refresh immediately on wake up, going idle needs no run.
+                    if *idle.borrow_and_update() || *paused.borrow() {
+                        continue;
+                    }
+                    idle_ticks = 0;
+                    ticker.reset();
+                }
+                // Requested over the control socket, also while paused.
+                () = slot.refresh.notified() => {}
+                Ok(()) = paused.changed() => {
+                    // Refresh on resume, pausing needs no run.
+                    if *paused.borrow_and_update() {
+                        continue;
+                    }
+                    ticker.reset();
+                }
+            }
 
-            let output = match self.source.output().await {
-                Ok(output) if output.is_empty() => self.default.to_string(),
-                Ok(output) => output,
+            let started = Instant::now();
+            let output = self.source.output().await;
+            if log::enabled(Level::Debug) {
+                log::debug(
+                    Some(&self.label()),
+                    format!("ran in {:.1?}: {output:?}", started.elapsed()),
+                );
+            }
+
+            retry_at = None;
+            let output = match output {
+                Ok(output) => {
+                    failures = 0;
+                    slot.failures.set(0);
+                    last_success = Instant::now();
+
+                    let output = self.success_output(output, replace_marker);
+                    last_good.insert(output).clone()
+                }
                 Err(err) => {
-                    eprintln!("{}: {err}", self.source.label());
-                    "err".to_string()
+                    log::status_error(&self.label(), &err);
+
+                    if let Some(smoother) = &mut self.smoother {
+                        smoother.clear_raw();
+                    }
+
+                    failures += 1;
+                    slot.failures.set(failures);
+                    slot.errors.set(slot.errors.get() + 1);
+                    *slot.last_error.borrow_mut() = Some(err.to_string());
+
+                    if let Some(retry) = self.retry
+                        && failures <= retry.max_attempts
+                    {
+                        let delay = retry.delay(failures - 1);
+                        // Delays too long to represent are left to the schedule.
+                        retry_at = Instant::now().checked_add(delay);
+                        if retry_at.is_some() {
+                            log::debug(Some(&self.label()), format!("retrying in {delay:.1?}"));
+                        }
+                    }
+
+                    if self.hide_when.contains(&HideWhen::Error) {
+                        Block::default()
+                    } else if let Some(output) = self.error_output(
+                        &err,
+                        last_good.as_ref(),
+                        failures,
+                        last_success.elapsed(),
+                        replace_marker,
+                    ) {
+                        output
+                    } else {
+                        continue;
+                    }
                 }
             };
 
-            *shared_output.borrow_mut() = self.format_value(&output, replace_marker);
+            slot.set_output(output);
         }
     }
 }
 
+/// Result of `Status::run_once`.
+#[derive(Debug)]
+pub struct SingleRun {
+    pub label: String,
+    /// Time the source took to produce its output.
+    pub elapsed: Duration,
+    /// Raw source output.
+    pub result: Result<String>,
+    /// Text shown on the bar, empty if hidden.
+    pub shown: String,
+}
+
+impl fmt::Display for SingleRun {
+    /// One line with the label, time, and the shown text or the error.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<24} {:>9.1?}  ", self.label, self.elapsed)?;
+        match &self.result {
+            Ok(_) => write!(f, "ok     `{}`", self.shown),
+            Err(err) => write!(f, "error  {err} (shown `{}`)", self.shown),
+        }
+    }
+}
+
+/// Output of a status on the bar; empty if hidden.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Block {
+    /// The whole output, or the part of `format` before a scrolling value.
+    before: String,
+    marquee: Option<Marquee>,
+    /// The part of `format` after a scrolling value.
+    after: String,
+}
+
+impl From<String> for Block {
+    fn from(output: String) -> Self {
+        Self {
+            before: output,
+            ..Self::default()
+        }
+    }
+}
+
+impl Block {
+    fn is_hidden(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn render(&self) -> String {
+        match &self.marquee {
+            Some(marquee) => format!("{}{}{}", self.before, marquee.window(), self.after),
+            None => self.before.clone(),
+        }
+    }
+
+    /// Scrolls a marquee by one step.
+    fn advance(&mut self) {
+        if let Some(marquee) = &mut self.marquee {
+            marquee.advance();
+        }
+    }
+}
+
+/// Validates every status, and that status names and `External` source names are unique.
+pub fn validate(statuses: &[Status]) -> Result<()> {
+    for status in statuses {
+        status.validate()?;
+    }
+
+    let names: Vec<_> = statuses.iter().filter_map(|status| status.name).collect();
+    if let Some(name) = first_duplicate(&names) {
+        return Err(Error::config(format!(
+            "status `name` `{name}` is used more than once"
+        )));
+    }
+
+    let externals: Vec<_> = statuses
+        .iter()
+        .filter_map(|status| status.source.inbox())
+        .map(|(name, _)| name)
+        .collect();
+    if let Some(name) = first_duplicate(&externals) {
+        return Err(Error::config(format!(
+            "external source name `{name}` is used more than once"
+        )));
+    }
+
+    Ok(())
+}
+
+fn first_duplicate<'a>(names: &[&'a str]) -> Option<&'a str> {
+    names
+        .iter()
+        .enumerate()
+        .find_map(|(index, name)| names[..index].contains(name).then_some(*name))
+}
+
+/// Joins the shown outputs; empty outputs are hidden together with their separator.
+fn join_outputs(outputs: impl IntoIterator<Item = String>, separator: &str) -> String {
+    let mut accumulated_output = String::new();
+
+    for output in outputs {
+        if output.is_empty() {
+            continue;
+        }
+
+        if !accumulated_output.is_empty() {
+            accumulated_output.push_str(separator);
+        }
+        accumulated_output.push_str(&output);
+    }
+
+    accumulated_output
+}
+
+/// State of a status shared by its run loop, the bar writer and the control socket.
+#[derive(Debug, Default)]
+struct Slot {
+    name: Option<&'static str>,
+    /// Label of the source.
+    label: String,
+    output: RefCell<Block>,
+    /// Shown instead of `output` while set over the control socket.
+    override_text: RefCell<Option<String>>,
+    /// Wakes the run loop for a refresh requested over the control socket.
+    refresh: Notify,
+    /// Consecutive failed runs.
+    failures: Cell<u32>,
+    /// Failed runs since the start.
+    errors: Cell<u64>,
+    last_error: RefCell<Option<String>>,
+    /// Name and sender of an `External` source.
+    inbox: Option<(String, sources::Inbox)>,
+}
+
+impl Slot {
+    /// Replaces the output; a marquee keeps scrolling from its position if its text is unchanged.
+    fn set_output(&self, mut output: Block) {
+        let mut current = self.output.borrow_mut();
+        if let (Some(marquee), Some(previous)) = (&mut output.marquee, &current.marquee) {
+            marquee.continue_from(previous);
+        }
+        *current = output;
+    }
+
+    fn shown(&self) -> String {
+        match &*self.override_text.borrow() {
+            Some(text) => text.clone(),
+            None => self.output.borrow().render(),
+        }
+    }
+}
+
+/// Completes at `at`, never if there is no retry pending.
+async fn retry_timer(at: Option<Instant>) {
+    match at {
+        Some(at) => tokio::time::sleep_until(at).await,
+        None => std::future::pending().await,
+    }
+}
+
 #[derive(Debug)]
 pub struct Bar {
     statuses: Vec<Status>,
-    x11rb: X11rb,
+    /// Root window name the bar is written to; without it the bar only goes to stdout.
+    x11rb: Option<X11rb>,
     /// Default replace marker is `{}`
     replace_marker: String,
     separator: String,
@@ -68,19 +741,35 @@ pub struct Bar {
     write_to_stdout: bool,
     /// Write output only when the content has changed
     write_on_changes: bool,
+    idle_policy: IdlePolicy,
+    /// No user input for this long also counts as idle, besides a blanked screen.
+    idle_after: Option<Duration>,
+    /// Unix socket accepting `control::Command`s while running.
+    control_socket: Option<PathBuf>,
 }
 
 impl Bar {
-    pub fn new(statuses: Vec<Status>, x11rb: X11rb) -> Self {
-        Self {
+    /// Fails with `Error::Config` if a status is invalid, see `validate`.
+    pub fn new(statuses: Vec<Status>) -> Result<Self> {
+        validate(&statuses)?;
+
+        Ok(Self {
             statuses,
-            x11rb,
+            x11rb: None,
             replace_marker: String::from("{}"),
             separator: String::new(),
             write_interval: Duration::from_millis(500),
             write_to_stdout: true,
             write_on_changes: false,
-        }
+            idle_policy: IdlePolicy::Ignore,
+            idle_after: None,
+            control_socket: None,
+        })
+    }
+
+    pub fn with_x11(mut self, x11rb: X11rb) -> Self {
+        self.x11rb = Some(x11rb);
+        self
     }
 
     pub fn with_replace_marker(mut self, marker: &str) -> Self {
@@ -108,13 +797,30 @@ impl Bar {
         self
     }
 
+    pub const fn with_idle_policy(mut self, idle_policy: IdlePolicy) -> Self {
+        self.idle_policy = idle_policy;
+        self
+    }
+
+    pub const fn with_idle_after(mut self, idle_after: Duration) -> Self {
+        self.idle_after = Some(idle_after);
+        self
+    }
+
+    pub fn with_control_socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.control_socket = Some(path.into());
+        self
+    }
+
+    #[allow(clippy::too_many_arguments)]
     async fn write_output(
         write_interval: Duration,
-        outputs: &[RefCell<String>],
+        slots: &[Slot],
         separator: &str,
-        x11rb: &mut X11rb,
+        mut x11rb: Option<&mut X11rb>,
         write_to_stdout: bool,
         write_on_changes: bool,
+        idle: Option<(&watch::Sender<bool>, Option<Duration>)>,
     ) {
         let mut interval = tokio::time::interval(write_interval);
         interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
@@ -124,19 +830,30 @@ impl Bar {
         loop {
             interval.tick().await;
 
-            let mut accumulated_output = String::new();
-            let mut outputs_iter = outputs.iter().peekable();
-            while let Some(output) = outputs_iter.next() {
-                accumulated_output.push_str(&output.borrow());
-
-                if outputs_iter.peek().is_some() {
-                    accumulated_output.push_str(separator);
+            if let Some((idle, idle_after)) = idle
+                && let Some(x11rb) = &mut x11rb
+            {
+                match x11rb.is_idle(idle_after) {
+                    Ok(is_idle) => {
+                        if idle.send_if_modified(|idle| std::mem::replace(idle, is_idle) != is_idle)
+                        {
+                            log::debug(None, if is_idle { "idle" } else { "active" });
+                        }
+                    }
+                    Err(err) => log::warn(None, format!("error querying screen idle state: {err}")),
                 }
             }
 
+            let accumulated_output = join_outputs(slots.iter().map(Slot::shown), separator);
+            for slot in slots {
+                slot.output.borrow_mut().advance();
+            }
+
             if !write_on_changes || accumulated_output != last_push {
                 // X11rb handles reconnection internally; retry on next tick if it fails
-                let write_ok = x11rb.set_root_win_name(&accumulated_output).is_ok();
+                let write_ok = x11rb
+                    .as_mut()
+                    .is_none_or(|x11rb| x11rb.set_root_win_name(&accumulated_output).is_ok());
 
                 if write_to_stdout {
                     println!("{accumulated_output}");
@@ -149,45 +866,543 @@ impl Bar {
         }
     }
 
-    async fn run_inner(&mut self) {
-        let shared_outputs: Vec<RefCell<String>> = self
+    async fn run_inner(&mut self, control: Option<UnixListener>) {
+        let slots: Vec<Slot> = self
             .statuses
             .iter()
-            .map(|c| RefCell::new(c.default_output(&self.replace_marker)))
+            .map(|status| Slot {
+                name: status.name,
+                label: status.source.label(),
+                output: RefCell::new(status.default_output(&self.replace_marker)),
+                inbox: status
+                    .source
+                    .inbox()
+                    .map(|(name, inbox)| (name.to_string(), inbox)),
+                ..Slot::default()
+            })
             .collect();
+        let (idle_sender, idle) = watch::channel(false);
+        let (paused_sender, paused) = watch::channel(false);
 
-        let run_futures = join_all(
-            self.statuses
-                .iter_mut()
-                .zip(shared_outputs.iter())
-                .map(|(status, output)| status.run(output, &self.replace_marker)),
-        );
+        let run_futures = join_all(self.statuses.iter_mut().zip(slots.iter()).map(
+            |(status, slot)| {
+                status.run(
+                    slot,
+                    &self.replace_marker,
+                    idle.clone(),
+                    self.idle_policy,
+                    paused.clone(),
+                )
+            },
+        ));
         let write_output_future = Self::write_output(
             self.write_interval,
-            &shared_outputs,
+            &slots,
             &self.separator,
-            &mut self.x11rb,
+            self.x11rb.as_mut(),
             self.write_to_stdout,
             self.write_on_changes,
+            (self.idle_policy != IdlePolicy::Ignore).then_some((&idle_sender, self.idle_after)),
         );
+        let control_future = async {
+            match control {
+                Some(listener) => control::serve(listener, &slots, &paused_sender).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::join!(run_futures, write_output_future, control_future);
+    }
+
+    /// Runs every status once concurrently, returning the composed bar without writing it, and
+    /// the run of each status in order.
+    pub async fn once(&mut self) -> (String, Vec<SingleRun>) {
+        let runs = join_all(
+            self.statuses
+                .iter_mut()
+                .map(|status| status.run_once(&self.replace_marker)),
+        )
+        .await;
 
-        tokio::join!(run_futures, write_output_future);
+        let output = join_outputs(runs.iter().map(|run| run.shown.clone()), &self.separator);
+        (output, runs)
     }
 
     pub async fn run(&mut self) {
         let mut sigterm = signal::unix::signal(signal::unix::SignalKind::terminate())
             .expect("failed to instantiate unix SIGTERM handler");
 
+        let control = match &self.control_socket {
+            Some(path) => control::bind(path)
+                .await
+                .inspect_err(|err| log::warn(None, format!("control socket disabled: {err}")))
+                .ok(),
+            None => None,
+        };
+        let bound = control.is_some();
+
         tokio::select! {
-            () = self.run_inner() => {
-                eprintln!("status bar exited unexpectedly");
+            () = self.run_inner(control) => {
+                log::error(None, "status bar exited unexpectedly");
             }
             _ = signal::ctrl_c() => {
-                eprintln!("received SIGINT (Ctrl+C), shutting down gracefully");
+                log::info(None, "received SIGINT (Ctrl+C), shutting down gracefully");
             }
             _ = sigterm.recv() => {
-                eprintln!("received SIGTERM, shutting down gracefully");
+                log::info(None, "received SIGTERM, shutting down gracefully");
             }
         }
+
+        if bound && let Some(path) = &self.control_socket {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use std::{cell::Cell, rc::Rc};
+
+    use futures::future::LocalBoxFuture;
+    use tokio::{sync::watch, time::sleep};
+
+    use super::{
+        Block, Error, ErrorPolicy, HideWhen, IdlePolicy, Retry, SingleRun, Slot, Smoothing,
+        Staleness, Status, first_duplicate, join_outputs,
+        schedule::{Align, Cron, Schedule},
+        sources::{BuiltIn, Source},
+        validate,
+        width::{Alignment, Overflow, Width},
+    };
+    use crate::error::{ErrorKind, Result};
+
+    /// Source always showing the same value.
+    #[derive(Debug)]
+    struct Fixed(&'static str);
+
+    impl Source for Fixed {
+        fn label(&self) -> String {
+            String::from("fixed")
+        }
+
+        fn output(&mut self) -> LocalBoxFuture<'_, Result<String>> {
+            Box::pin(async { Ok(self.0.to_string()) })
+        }
+    }
+
+    fn block(text: &str) -> Block {
+        Block::from(text.to_string())
+    }
+
+    /// Source counting its runs.
+    #[derive(Debug)]
+    struct Counter(Rc<Cell<u32>>);
+
+    impl Source for Counter {
+        fn label(&self) -> String {
+            String::from("counter")
+        }
+
+        fn output(&mut self) -> LocalBoxFuture<'_, Result<String>> {
+            self.0.set(self.0.get() + 1);
+            Box::pin(async { Ok(self.0.get().to_string()) })
+        }
+    }
+
+    /// Source failing on every run, counting its runs.
+    #[derive(Debug)]
+    struct Failing(Rc<Cell<u32>>);
+
+    impl Source for Failing {
+        fn label(&self) -> String {
+            String::from("failing")
+        }
+
+        fn output(&mut self) -> LocalBoxFuture<'_, Result<String>> {
+            self.0.set(self.0.get() + 1);
+            Box::pin(async { Err(error()) })
+        }
+    }
+
+    /// Status running every second, and its run count.
+    fn counting() -> (Status, Rc<Cell<u32>>) {
+        let runs = Rc::new(Cell::new(0));
+        let status = Status::new(
+            Counter(runs.clone()),
+            Schedule::Interval(Duration::from_secs(1)),
+        );
+        (status, runs)
+    }
+
+    fn error() -> Error {
+        Error::CommandTimeout {
+            command: String::from("curl"),
+            timeout: Duration::from_secs(1),
+        }
+    }
+
+    fn status(error_policy: ErrorPolicy) -> Status {
+        Status::new(BuiltIn::Ram, Schedule::Interval(Duration::from_secs(1)))
+            .with_format("<{}>")
+            .with_error_policy(error_policy)
+    }
+
+    #[test]
+    fn validates_statuses() {
+        let interval = Schedule::Interval(Duration::from_secs(1));
+        let retry = Retry {
+            max_attempts: 1,
+            initial_delay: Duration::from_secs(1),
+            multiplier: -1.0,
+            max_delay: Duration::from_secs(5),
+        };
+
+        assert!(validate(&[status(ErrorPolicy::Show).with_name("ram")]).is_ok());
+        for statuses in [
+            [Status::new(
+                BuiltIn::Ram,
+                Schedule::Interval(Duration::ZERO),
+            )],
+            [Status::new(BuiltIn::Ram, Schedule::Aligned(Align::Auto))],
+            [Status::new(BuiltIn::Ram, interval.clone()).with_name("1")],
+            [Status::new(BuiltIn::Ram, interval.clone()).with_retry(retry)],
+            [Status::new(BuiltIn::Ram, interval.clone()).with_smoothing(Smoothing::Ema(0.0))],
+        ] {
+            assert!(validate(&statuses).is_err(), "{statuses:?}");
+        }
+        assert!(
+            validate(&[
+                Status::new(BuiltIn::Ram, interval.clone()).with_name("ram"),
+                Status::new(BuiltIn::cpu(), interval).with_name("ram"),
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn finds_duplicate_names() {
+        assert_eq!(first_duplicate(&["cpu", "ram", "date"]), None);
+        assert_eq!(first_duplicate(&["date", "cpu", "date"]), Some("date"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stretches_intervals_while_idle() {
+        let (mut status, runs) = counting();
+        let slot = Slot::default();
+        let (_idle, idle) = watch::channel(true);
+        let (_paused, paused) = watch::channel(false);
+
+        let run = status.run(&slot, "{}", idle, IdlePolicy::Stretch(3), paused);
+        let _ = tokio::time::timeout(Duration::from_millis(10_500), run).await;
+
+        // Every third of the ticks at 0s to 10s.
+        assert_eq!(runs.get(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pauses_while_idle_and_refreshes_on_wake_up() {
+        let (mut status, runs) = counting();
+        let slot = Slot::default();
+        let (idle_sender, idle) = watch::channel(true);
+        let (_paused, paused) = watch::channel(false);
+
+        let script = async {
+            sleep(Duration::from_millis(5_500)).await;
+            assert_eq!(runs.get(), 0);
+
+            idle_sender.send_replace(false);
+            sleep(Duration::from_millis(100)).await;
+            assert_eq!(runs.get(), 1);
+
+            // The interval restarts at the wake up.
+            sleep(Duration::from_secs(2)).await;
+            assert_eq!(runs.get(), 3);
+        };
+        tokio::select! {
+            () = status.run(&slot, "{}", idle, IdlePolicy::Pause, paused) => unreachable!(),
+            () = script => {}
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn runs_only_on_refresh_while_paused() {
+        let (mut status, runs) = counting();
+        let slot = Slot::default();
+        let (_idle, idle) = watch::channel(false);
+        let (paused_sender, paused) = watch::channel(true);
+
+        let script = async {
+            sleep(Duration::from_millis(3_500)).await;
+            assert_eq!(runs.get(), 0);
+
+            slot.refresh.notify_one();
+            sleep(Duration::from_millis(100)).await;
+            assert_eq!(runs.get(), 1);
+
+            paused_sender.send_replace(false);
+            sleep(Duration::from_millis(100)).await;
+            assert_eq!(runs.get(), 2);
+        };
+        tokio::select! {
+            () = status.run(&slot, "{}", idle, IdlePolicy::Ignore, paused) => unreachable!(),
+            () = script => {}
+        }
+    }
+
+    #[test]
+    fn keeps_wall_clock_schedules_while_idle() {
+        let interval = Schedule::Interval(Duration::from_secs(1));
+        let cron = Schedule::Cron(Cron::parse("0,30 * * * *", chrono_tz::UTC).unwrap());
+
+        assert_eq!(
+            IdlePolicy::Stretch(10).for_schedule(&interval),
+            IdlePolicy::Stretch(10)
+        );
+        assert_eq!(
+            IdlePolicy::Stretch(10).for_schedule(&Schedule::Aligned(Align::Minute)),
+            IdlePolicy::Ignore
+        );
+        assert_eq!(IdlePolicy::Pause.for_schedule(&cron), IdlePolicy::Ignore);
+    }
+
+    #[test]
+    fn retry_delay_backs_off_up_to_max() {
+        let retry = Retry {
+            max_attempts: 5,
+            initial_delay: Duration::from_secs(5),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(30),
+        };
+
+        let delays = (0..5).map(|attempt| retry.delay(attempt).as_secs());
+        assert_eq!(delays.collect::<Vec<_>>(), [5, 10, 20, 30, 30]);
+
+        let retry = Retry {
+            multiplier: -1.0,
+            ..retry
+        };
+        assert_eq!(retry.delay(1), Duration::from_secs(30));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn skips_retries_too_far_ahead() {
+        let runs = Rc::new(Cell::new(0));
+        let mut status = Status::new(Failing(runs.clone()), Schedule::Interval(Duration::MAX))
+            .with_retry(Retry {
+                max_attempts: 3,
+                initial_delay: Duration::MAX,
+                multiplier: 2.0,
+                max_delay: Duration::MAX,
+            });
+        let slot = Slot::default();
+        let (_idle, idle) = watch::channel(false);
+        let (_paused, paused) = watch::channel(false);
+
+        let run = status.run(&slot, "{}", idle, IdlePolicy::Ignore, paused);
+        let _ = tokio::time::timeout(Duration::from_secs(60), run).await;
+
+        assert_eq!(runs.get(), 1);
+        assert_eq!(slot.failures.get(), 1);
+    }
+
+    #[test]
+    fn shows_error_by_default() {
+        let status = status(ErrorPolicy::Show);
+        assert_eq!(
+            status.error_output(&error(), Some(&block("<1>")), 1, Duration::ZERO, "{}"),
+            Some(block("<err>"))
+        );
+    }
+
+    #[test]
+    fn formats_error_by_kind() {
+        let status = status(ErrorPolicy::Show).with_error_format(|err| match err.kind() {
+            ErrorKind::CommandTimeout => String::from("⌛"),
+            _ => String::new(),
+        });
+        assert_eq!(
+            status.error_output(&error(), None, 1, Duration::ZERO, "{}"),
+            Some(block("<⌛>"))
+        );
+        assert_eq!(
+            status.error_output(&Error::io("/sys", "missing"), None, 1, Duration::ZERO, "{}"),
+            Some(Block::default())
+        );
+    }
+
+    #[test]
+    fn keeps_last_good_output() {
+        let status = status(ErrorPolicy::KeepLast);
+
+        assert_eq!(
+            status.error_output(&error(), Some(&block("<1>")), 3, Duration::ZERO, "{}"),
+            Some(block("<1>"))
+        );
+        assert_eq!(
+            status.error_output(&error(), None, 3, Duration::ZERO, "{}"),
+            None
+        );
+    }
+
+    #[test]
+    fn decorates_stale_output() {
+        let status = status(ErrorPolicy::Stale {
+            after: Staleness::Failures(2),
+            on_stale: "{}?",
+        });
+        assert_eq!(
+            status.error_output(&error(), Some(&block("<1>")), 1, Duration::ZERO, "{}"),
+            Some(block("<1>"))
+        );
+        assert_eq!(
+            status.error_output(&error(), Some(&block("<1>")), 2, Duration::ZERO, "{}"),
+            Some(block("<1>?"))
+        );
+
+        let status = Status {
+            error_policy: ErrorPolicy::Stale {
+                after: Staleness::Age(Duration::from_secs(60)),
+                on_stale: "{}?",
+            },
+            ..status
+        };
+        assert_eq!(
+            status.error_output(
+                &error(),
+                Some(&block("<1>")),
+                9,
+                Duration::from_secs(59),
+                "{}"
+            ),
+            Some(block("<1>"))
+        );
+        assert_eq!(
+            status.error_output(
+                &error(),
+                Some(&block("<1>")),
+                1,
+                Duration::from_secs(60),
+                "{}"
+            ),
+            Some(block("<1>?"))
+        );
+    }
+
+    #[test]
+    fn keeps_hidden_output_hidden_when_stale() {
+        let status = status(ErrorPolicy::Stale {
+            after: Staleness::Failures(1),
+            on_stale: "{}?",
+        });
+        assert_eq!(
+            status.error_output(&error(), Some(&Block::default()), 3, Duration::ZERO, "{}"),
+            Some(Block::default())
+        );
+    }
+
+    #[test]
+    fn hides_on_error() {
+        let status = status(ErrorPolicy::Hide);
+        assert_eq!(
+            status.error_output(&error(), Some(&block("<1>")), 1, Duration::ZERO, "{}"),
+            Some(Block::default())
+        );
+    }
+
+    #[test]
+    fn hide_rules_match_output() {
+        assert!(HideWhen::Empty.hides(""));
+        assert!(HideWhen::Equals("100").hides("100"));
+        assert!(HideWhen::Below(10.0).hides(" 5 "));
+        assert!(HideWhen::Above(99.0).hides("100"));
+
+        assert!(!HideWhen::Empty.hides("1"));
+        assert!(!HideWhen::Above(99.0).hides("muted"));
+        assert!(!HideWhen::Error.hides(""));
+    }
+
+    #[test]
+    fn joins_outputs_without_hidden_blocks() {
+        let outputs = ["", "a", "", "b", ""].map(String::from);
+        assert_eq!(join_outputs(outputs, " | "), "a | b");
+    }
+
+    #[test]
+    fn formats_value_with_filters() {
+        let status = status(ErrorPolicy::Show).with_format("{} B = {|bytes}/s");
+        assert_eq!(status.format_value("1536", "{}"), "1536 B = 1.5 KiB/s");
+    }
+
+    #[test]
+    fn renders_history_sparkline() {
+        let mut status = status(ErrorPolicy::Show)
+            .with_format("{}% {|spark:100}")
+            .with_history(3);
+        for sample in [0.0, 50.0, 100.0] {
+            status.history.as_mut().unwrap().push(sample);
+        }
+
+        assert_eq!(status.format_value("100", "{}"), "100% ▁▅█");
+    }
+
+    #[test]
+    fn formats_smoothed_and_raw_values() {
+        let mut status = status(ErrorPolicy::Show)
+            .with_format("{} ({raw})")
+            .with_smoothing(Smoothing::Ema(0.5));
+        let smoother = status.smoother.as_mut().unwrap();
+        smoother.smooth("10".to_string());
+        let smoothed = smoother.smooth("20".to_string());
+
+        assert_eq!(status.format_value(&smoothed, "{}"), "15 (20)");
+    }
+
+    #[tokio::test]
+    async fn marquee_keeps_scrolling_while_value_is_unchanged() {
+        let mut status = Status::new(Fixed("abcdef"), Schedule::Interval(Duration::from_secs(1)))
+            .with_format("{|spark:10}[{}]")
+            .with_history(2)
+            .with_width(Width {
+                min: 0,
+                max: Some(4),
+                alignment: Alignment::Left,
+                overflow: Overflow::Marquee,
+            });
+        status.history.as_mut().unwrap().push(10.0);
+        let slot = Slot::default();
+
+        for _ in 0..2 {
+            let output = status.source.output().await.unwrap();
+            slot.set_output(status.success_output(output, "{}"));
+            slot.output.borrow_mut().advance();
+        }
+        assert_eq!(slot.shown(), "█[cdef]");
+
+        slot.set_output(status.success_output(String::from("ghijkl"), "{}"));
+        assert_eq!(slot.shown(), "█[ghij]");
+    }
+
+    #[test]
+    fn describes_single_runs() {
+        let run = |result, shown: &str| SingleRun {
+            label: String::from("cpu"),
+            elapsed: Duration::from_micros(2100),
+            result,
+            shown: shown.to_string(),
+        };
+
+        assert_eq!(
+            run(Ok(String::from("12")), "<12>").to_string(),
+            format!("{:<24} {:>9}  ok     `<12>`", "cpu", "2.1ms")
+        );
+        assert_eq!(
+            run(Err(error()), "err").to_string(),
+            format!(
+                "{:<24} {:>9}  error  `curl` timed out after 1s (shown `err`)",
+                "cpu", "2.1ms"
+            )
+        );
     }
 }
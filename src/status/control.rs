@@ -0,0 +1,394 @@
+use std::{
+    io,
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+};
+
+use futures::{StreamExt, stream::FuturesUnordered};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::watch,
+};
+
+use super::Slot;
+use crate::{
+    error::{Error, Result},
+    log,
+};
+
+/// Command accepted by the control socket, one per line, e.g. `refresh 2`.
+///
+/// Statuses are referred to by their name, their position in the bar starting at `0`, or their
+/// source label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// `refresh [status]`: runs the status now, every status without one.
+    Refresh(Option<String>),
+    /// `pause`: stops running statuses until `resume`.
+    Pause,
+    /// `resume`: refreshes every status and continues their schedules.
+    Resume,
+    /// `override <status> <text>`: shows `text` instead of the status output. Kept unsplit
+    /// until the statuses are known, since labels like ``battery `BAT0` `` contain spaces.
+    Override(String),
+    /// `clear <status>`: shows the status output again.
+    Clear(String),
+    /// `dump`: current outputs and error counts as a JSON object.
+    Dump,
+    /// `set <name> [text]`: sets the value of the `External` source `name`, reverting it to the
+    /// status default without a text.
+    Set(String, Option<String>),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self> {
+        let line = line.trim();
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim_start();
+        let status = || {
+            (!rest.is_empty()).then(|| rest.to_string()).ok_or_else(|| {
+                Error::parse("control command", format!("`{command}` needs a status"))
+            })
+        };
+
+        match command {
+            "refresh" => Ok(Self::Refresh((!rest.is_empty()).then(|| rest.to_string()))),
+            "pause" => Ok(Self::Pause),
+            "resume" => Ok(Self::Resume),
+            "override" if rest.contains(' ') => Ok(Self::Override(rest.to_string())),
+            "override" => Err(Error::parse(
+                "control command",
+                "`override` needs a status and a text",
+            )),
+            "clear" => Ok(Self::Clear(status()?)),
+            "dump" => Ok(Self::Dump),
+            "set" => Ok(match rest.split_once(' ') {
+                Some((name, text)) => {
+                    Self::Set(name.to_string(), Some(text.trim_start().to_string()))
+                }
+                None => Self::Set(status()?, None),
+            }),
+            _ => Err(Error::parse(
+                "control command",
+                format!("unknown command `{line}`"),
+            )),
+        }
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/stsr.sock`, if the runtime directory is set.
+pub fn default_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_RUNTIME_DIR").map(|dir| PathBuf::from(dir).join("stsr.sock"))
+}
+
+/// Listens on `path`, replacing a stale socket left behind by an instance that crashed; any
+/// other file at `path` is kept and fails the bind.
+pub(super) async fn bind(path: &Path) -> Result<UnixListener> {
+    let context = path.display().to_string();
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if !metadata.file_type().is_socket() => {
+            return Err(Error::io(context, "exists and is not a socket"));
+        }
+        Ok(_) => {
+            if UnixStream::connect(path).await.is_ok() {
+                return Err(Error::io(context, "in use by another instance"));
+            }
+            std::fs::remove_file(path).map_err(|err| Error::io(&context, err))?;
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(Error::io(context, err)),
+    }
+
+    UnixListener::bind(path).map_err(|err| Error::io(context, err))
+}
+
+/// Answers clients until the bar stops; clients are served concurrently on this task.
+pub(super) async fn serve(listener: UnixListener, slots: &[Slot], paused: &watch::Sender<bool>) {
+    let mut clients = FuturesUnordered::new();
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => clients.push(handle(stream, slots, paused)),
+                Err(err) => log::warn(None, format!("control socket: {err}")),
+            },
+            Some(()) = clients.next() => {}
+        }
+    }
+}
+
+/// Replies to every line with `ok`, the JSON dump, or `error: <message>`.
+async fn handle(stream: UnixStream, slots: &[Slot], paused: &watch::Sender<bool>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        log::debug(None, format!("control command `{}`", line.trim()));
+
+        let reply = match Command::parse(&line).and_then(|command| execute(command, slots, paused))
+        {
+            Ok(reply) => reply,
+            Err(err) => format!("error: {err}"),
+        };
+        if writer
+            .write_all(format!("{reply}\n").as_bytes())
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+fn execute(command: Command, slots: &[Slot], paused: &watch::Sender<bool>) -> Result<String> {
+    match command {
+        Command::Refresh(None) => slots.iter().for_each(|slot| slot.refresh.notify_one()),
+        Command::Refresh(Some(status)) => find(slots, &status)?.refresh.notify_one(),
+        Command::Pause => {
+            paused.send_replace(true);
+        }
+        Command::Resume => {
+            paused.send_replace(false);
+        }
+        Command::Override(line) => {
+            let (slot, text) = find_with_text(slots, &line)?;
+            *slot.override_text.borrow_mut() = Some(text.to_string());
+        }
+        Command::Clear(status) => *find(slots, &status)?.override_text.borrow_mut() = None,
+        Command::Dump => return Ok(dump(slots, *paused.borrow())),
+        Command::Set(name, text) => {
+            let (_, inbox) = slots
+                .iter()
+                .filter_map(|slot| slot.inbox.as_ref())
+                .find(|(external, _)| *external == name)
+                .ok_or_else(|| {
+                    Error::parse("control command", format!("no external source `{name}`"))
+                })?;
+            // Fails only once the bar stops.
+            let _ = inbox.send(text.unwrap_or_default());
+        }
+    }
+
+    Ok(String::from("ok"))
+}
+
+fn find<'a>(slots: &'a [Slot], status: &str) -> Result<&'a Slot> {
+    slots
+        .iter()
+        .enumerate()
+        .find(|(index, slot)| {
+            slot.name == Some(status) || index.to_string() == status || slot.label == status
+        })
+        .map(|(_, slot)| slot)
+        .ok_or_else(|| Error::parse("control command", format!("no status `{status}`")))
+}
+
+/// Splits `<status> <text>` at the longest status prefix, so the status may contain spaces.
+fn find_with_text<'a, 'b>(slots: &'a [Slot], line: &'b str) -> Result<(&'a Slot, &'b str)> {
+    line.match_indices(' ')
+        .rev()
+        .find_map(|(at, _)| {
+            let slot = find(slots, &line[..at]).ok()?;
+            Some((slot, line[at..].trim_start()))
+        })
+        .ok_or_else(|| Error::parse("control command", format!("no status in `{line}`")))
+}
+
+/// Current state as `{"paused":false,"statuses":[{"index":0,"name":"cpu",...}]}`.
+fn dump(slots: &[Slot], paused: bool) -> String {
+    let statuses: Vec<String> = slots
+        .iter()
+        .enumerate()
+        .map(|(index, slot)| {
+            format!(
+                r#"{{"index":{index},"name":{},"label":{},"value":{},"override":{},"failures":{},"errors":{},"last_error":{}}}"#,
+                json_option(slot.name),
+                json_string(&slot.label),
+                json_string(&slot.output.borrow().render()),
+                json_option(slot.override_text.borrow().as_deref()),
+                slot.failures.get(),
+                slot.errors.get(),
+                json_option(slot.last_error.borrow().as_deref()),
+            )
+        })
+        .collect();
+
+    format!(
+        r#"{{"paused":{paused},"statuses":[{}]}}"#,
+        statuses.join(",")
+    )
+}
+
+fn json_option(text: Option<&str>) -> String {
+    text.map_or_else(|| String::from("null"), json_string)
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::from('"');
+
+    for char in text.chars() {
+        match char {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            char if char.is_control() => json.push_str(&format!("\\u{:04x}", char as u32)),
+            char => json.push(char),
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, time::Duration};
+
+    use tokio::sync::watch;
+
+    use super::{Command, bind, execute, json_string};
+    use crate::status::{
+        Block, Slot,
+        sources::{BuiltIn, Source},
+    };
+
+    fn slot(label: &str, output: &str) -> Slot {
+        Slot {
+            name: (label == "cpu").then_some("load"),
+            label: label.to_string(),
+            output: RefCell::new(Block::from(output.to_string())),
+            ..Slot::default()
+        }
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(Command::parse("refresh").unwrap(), Command::Refresh(None));
+        assert_eq!(
+            Command::parse("refresh 2\n").unwrap(),
+            Command::Refresh(Some(String::from("2")))
+        );
+        assert_eq!(
+            Command::parse("override 0 on  break").unwrap(),
+            Command::Override(String::from("0 on  break"))
+        );
+        assert_eq!(
+            Command::parse("clear cpu").unwrap(),
+            Command::Clear(String::from("cpu"))
+        );
+        assert_eq!(
+            Command::parse("set ci failed 2/3").unwrap(),
+            Command::Set(String::from("ci"), Some(String::from("failed 2/3")))
+        );
+        assert_eq!(
+            Command::parse("set ci").unwrap(),
+            Command::Set(String::from("ci"), None)
+        );
+
+        for line in ["", "restart", "override 0", "clear", "set"] {
+            assert!(Command::parse(line).is_err(), "{line}");
+        }
+    }
+
+    #[tokio::test]
+    async fn replaces_only_stale_sockets() {
+        let path = std::env::temp_dir().join(format!("stsr-control-{}", std::process::id()));
+
+        std::fs::write(&path, "notes").unwrap();
+        assert!(bind(&path).await.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "notes");
+        std::fs::remove_file(&path).unwrap();
+
+        let listener = bind(&path).await.unwrap();
+        assert!(bind(&path).await.is_err());
+        drop(listener);
+        // Left behind like by a crashed instance.
+        assert!(bind(&path).await.is_ok());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn escapes_json_strings() {
+        assert_eq!(json_string("a \"b\"\\\n\u{1}"), r#""a \"b\"\\\n\u0001""#);
+    }
+
+    #[test]
+    fn executes_commands() {
+        let slots = [slot("cpu", "12%"), slot("ram", "40%")];
+        let (paused, _) = watch::channel(false);
+        let run = |line| execute(Command::parse(line).unwrap(), &slots, &paused);
+
+        assert_eq!(run("override ram full").unwrap(), "ok");
+        assert_eq!(run("refresh load").unwrap(), "ok");
+        assert_eq!(slots[1].shown(), "full");
+        assert!(run("override disk full").is_err());
+
+        assert_eq!(run("pause").unwrap(), "ok");
+        slots[0].errors.set(2);
+        *slots[0].last_error.borrow_mut() = Some(String::from("config: \"x\""));
+        assert_eq!(
+            run("dump").unwrap(),
+            concat!(
+                r#"{"paused":true,"statuses":["#,
+                r#"{"index":0,"name":"load","label":"cpu","value":"12%","override":null,"failures":0,"errors":2,"last_error":"config: \"x\""},"#,
+                r#"{"index":1,"name":null,"label":"ram","value":"40%","override":"full","failures":0,"errors":0,"last_error":null}]}"#,
+            )
+        );
+
+        assert_eq!(run("clear 1").unwrap(), "ok");
+        assert_eq!(slots[1].shown(), "40%");
+    }
+
+    #[test]
+    fn overrides_labels_with_spaces() {
+        let slots = [slot("battery", "90%"), slot("battery `BAT0`", "80%")];
+        let (paused, _) = watch::channel(false);
+        let run = |line| execute(Command::parse(line).unwrap(), &slots, &paused);
+
+        assert_eq!(run("override battery `BAT0` on  AC").unwrap(), "ok");
+        assert_eq!(slots[0].shown(), "90%");
+        assert_eq!(slots[1].shown(), "on  AC");
+        assert_eq!(run("clear battery `BAT0`").unwrap(), "ok");
+        assert_eq!(slots[1].shown(), "80%");
+        assert!(run("override bat full").is_err());
+    }
+
+    #[tokio::test]
+    async fn sets_external_values() {
+        let mut external = BuiltIn::external("ci", None, Some(Duration::from_millis(50)));
+        let slots = [Slot {
+            inbox: external
+                .inbox()
+                .map(|(name, inbox)| (name.to_string(), inbox)),
+            ..slot("external `ci`", "")
+        }];
+        let (paused, _) = watch::channel(false);
+        let run = |line| execute(Command::parse(line).unwrap(), &slots, &paused);
+
+        assert_eq!(run("set ci passed").unwrap(), "ok");
+        external.changed().await.unwrap();
+        assert_eq!(external.output().await.unwrap(), "passed");
+
+        // Without a text, the value reverts to the status default.
+        assert_eq!(run("set ci").unwrap(), "ok");
+        external.changed().await.unwrap();
+        assert_eq!(external.output().await.unwrap(), "");
+
+        assert_eq!(run("set ci failed").unwrap(), "ok");
+        external.changed().await.unwrap();
+        assert_eq!(external.output().await.unwrap(), "failed");
+        // Expires after the ttl.
+        external.changed().await.unwrap();
+        assert_eq!(external.output().await.unwrap(), "");
+
+        assert!(run("set cd passed").is_err());
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::VecDeque;
+
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Most recent numeric outputs of a status, oldest first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct History {
+    samples: VecDeque<f64>,
+    length: usize,
+}
+
+impl History {
+    pub fn new(length: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(length),
+            length,
+        }
+    }
+
+    /// Adds `sample`, dropping the oldest one once `length` samples are kept.
+    pub fn push(&mut self, sample: f64) {
+        if self.length == 0 {
+            return;
+        }
+        if self.samples.len() == self.length {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// One bar per sample, scaled from `0` to `max`, or to the largest sample if `None`.
+    pub fn sparkline(&self, max: Option<f64>) -> String {
+        let max = max.unwrap_or_else(|| self.samples.iter().copied().fold(0.0, f64::max));
+
+        self.samples
+            .iter()
+            .map(|sample| {
+                let level = if max > 0.0 { sample / max } else { 0.0 };
+                let index = (level * (BARS.len() - 1) as f64).round();
+                BARS[index.clamp(0.0, (BARS.len() - 1) as f64) as usize]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::History;
+
+    #[test]
+    fn keeps_the_latest_samples() {
+        let mut history = History::new(3);
+        for sample in [1.0, 2.0, 3.0, 4.0] {
+            history.push(sample);
+        }
+
+        assert_eq!(history.samples, [2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn renders_sparkline() {
+        let mut history = History::new(5);
+        for sample in [0.0, 25.0, 50.0, 75.0, 100.0] {
+            history.push(sample);
+        }
+
+        assert_eq!(history.sparkline(None), "▁▃▅▆█");
+        assert_eq!(history.sparkline(Some(200.0)), "▁▂▃▄▅");
+    }
+
+    #[test]
+    fn clamps_to_max_and_handles_zero() {
+        let mut history = History::new(2);
+        history.push(0.0);
+        assert_eq!(history.sparkline(None), "▁");
+
+        history.push(150.0);
+        assert_eq!(history.sparkline(Some(100.0)), "▁█");
+    }
+}
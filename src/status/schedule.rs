@@ -0,0 +1,415 @@
+use std::{
+    fs::File,
+    future::pending,
+    io::{self, Read},
+    os::fd::{AsRawFd, FromRawFd},
+};
+
+use chrono::{DateTime, TimeDelta, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use tokio::{
+    io::unix::AsyncFd,
+    time::{Duration, Interval, MissedTickBehavior},
+};
+
+use super::sources::Source;
+use crate::{
+    error::{Error, Result},
+    log,
+};
+
+pub use cron::Cron;
+
+mod cron;
+
+/// Parses durations such as `250ms`, `2s`, `5m`, `1h` or `1m30s`.
+pub fn duration(spec: &str) -> Result<Duration> {
+    let invalid = || Error::config(format!("invalid duration `{spec}`"));
+
+    let mut total = Duration::ZERO;
+    let mut rest = spec.trim();
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|char: char| !char.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        let value = rest[..digits].parse::<u64>().map_err(|_| invalid())?;
+        rest = &rest[digits..];
+
+        let unit = rest
+            .find(|char: char| char.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let part = match &rest[..unit] {
+            "ms" => Duration::from_millis(value),
+            "s" => Duration::from_secs(value),
+            "m" => Duration::from_secs(value.saturating_mul(60)),
+            "h" => Duration::from_secs(value.saturating_mul(3600)),
+            _ => return Err(invalid()),
+        };
+        rest = &rest[unit..];
+
+        total = total.saturating_add(part);
+    }
+
+    Ok(total)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// Run every period from start; missed ticks are skipped for long runs.
+    Interval(Duration),
+    /// Run at start and then on every wall-clock boundary of the given unit.
+    Aligned(Align),
+    /// Run at start and then at every minute matching the cron expression.
+    Cron(Cron),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    /// Derived from the format of a `DateTime` source, e.g. `Minute` for `%H:%M`.
+    Auto,
+}
+
+impl Align {
+    /// Smallest unit shown by a chrono `format`.
+    pub fn from_format(format: &str) -> Self {
+        let mut align = Self::Day;
+        let mut chars = format.chars();
+
+        while let Some(char) = chars.next() {
+            if char != '%' {
+                continue;
+            }
+
+            // Skip padding modifiers and widths such as `%-M`, `%3f` or `%:z`.
+            let Some(specifier) = chars
+                .by_ref()
+                .find(|char| !matches!(char, '-' | '_' | '0'..='9' | '.' | ':' | '#'))
+            else {
+                break;
+            };
+
+            match specifier {
+                'S' | 'T' | 's' | 'X' | 'r' | 'c' | 'f' | '+' => return Self::Second,
+                'M' | 'R' => align = Self::Minute,
+                'H' | 'I' | 'k' | 'l' | 'p' | 'P' if align == Self::Day => align = Self::Hour,
+                _ => {}
+            }
+        }
+
+        align
+    }
+
+    /// First boundary of this unit after `now`; `Auto` has to be resolved beforehand.
+    fn next_boundary(self, now: DateTime<Tz>) -> DateTime<Utc> {
+        let (truncated, step) = match self {
+            Self::Second | Self::Auto => (now.with_nanosecond(0), TimeDelta::seconds(1)),
+            Self::Minute => (
+                now.with_nanosecond(0).and_then(|now| now.with_second(0)),
+                TimeDelta::minutes(1),
+            ),
+            Self::Hour => (
+                now.with_nanosecond(0)
+                    .and_then(|now| now.with_second(0))
+                    .and_then(|now| now.with_minute(0)),
+                TimeDelta::hours(1),
+            ),
+            Self::Day => {
+                let midnight = now
+                    .date_naive()
+                    .succ_opt()
+                    .and_then(|day| day.and_hms_opt(0, 0, 0))
+                    .and_then(|midnight| now.timezone().from_local_datetime(&midnight).earliest());
+
+                if let Some(midnight) = midnight {
+                    return midnight.with_timezone(&Utc);
+                }
+                (None, TimeDelta::hours(1))
+            }
+        };
+
+        match truncated {
+            Some(truncated) => (truncated + step).with_timezone(&Utc),
+            // Local time does not exist (e.g. a DST gap), retry a step later.
+            None => (now + step).with_timezone(&Utc),
+        }
+    }
+}
+
+/// Produces the run ticks of a `Status` for its `Schedule`.
+#[derive(Debug)]
+pub(super) enum Ticker {
+    Interval(Interval),
+    WallClock {
+        boundary: Boundary,
+        timer: Option<WallClockTimer>,
+        /// The first tick completes immediately.
+        started: bool,
+    },
+}
+
+impl Ticker {
+    pub fn new(schedule: &Schedule, source: &dyn Source) -> Self {
+        match *schedule {
+            Schedule::Interval(period) => {
+                let mut interval = tokio::time::interval(period);
+                interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+                Self::Interval(interval)
+            }
+            Schedule::Aligned(align) => {
+                let (align, timezone) = match source.clock() {
+                    Some((format, timezone)) => (
+                        if align == Align::Auto {
+                            Align::from_format(format)
+                        } else {
+                            align
+                        },
+                        timezone,
+                    ),
+                    None => (align, Tz::UTC),
+                };
+
+                Self::wall_clock(Boundary::Aligned(align, timezone))
+            }
+            Schedule::Cron(ref cron) => Self::wall_clock(Boundary::Cron(cron.clone())),
+        }
+    }
+
+    fn wall_clock(boundary: Boundary) -> Self {
+        let timer = WallClockTimer::new()
+            .inspect_err(|err| {
+                log::warn(
+                    None,
+                    format!("wall-clock timer unavailable, falling back to sleep: {err}"),
+                )
+            })
+            .ok();
+
+        Self::WallClock {
+            boundary,
+            timer,
+            started: false,
+        }
+    }
+
+    pub async fn tick(&mut self) {
+        match self {
+            Self::Interval(interval) => {
+                interval.tick().await;
+            }
+            Self::WallClock {
+                boundary,
+                timer,
+                started,
+            } => {
+                if !std::mem::replace(started, true) {
+                    return;
+                }
+
+                let Some(mut next) = boundary.next_after(Utc::now()) else {
+                    log::warn(None, format!("{boundary:?} never matches, no further runs"));
+                    return pending().await;
+                };
+                loop {
+                    let slept = match timer {
+                        Some(timer) => timer.sleep_until(next).await,
+                        None => {
+                            let remaining = (next - Utc::now()).to_std().unwrap_or_default();
+                            tokio::time::sleep(remaining).await;
+                            Ok(())
+                        }
+                    };
+                    if let Err(err) = slept {
+                        log::warn(
+                            None,
+                            format!("wall-clock timer failed, falling back to sleep: {err}"),
+                        );
+                        *timer = None;
+                    }
+
+                    let now = Utc::now();
+                    if now >= next {
+                        return;
+                    }
+
+                    // The clock was set back, the boundary may now be far away.
+                    match boundary.next_after(now) {
+                        Some(boundary) => next = boundary,
+                        None => return pending().await,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Restarts the interval period after an out-of-schedule run.
+    pub fn reset(&mut self) {
+        if let Self::Interval(interval) = self {
+            interval.reset();
+        }
+    }
+}
+
+/// Wall-clock times a `Ticker` wakes up at.
+#[derive(Debug)]
+pub(super) enum Boundary {
+    Aligned(Align, Tz),
+    Cron(Cron),
+}
+
+impl Boundary {
+    fn next_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Aligned(align, timezone) => {
+                Some(align.next_boundary(now.with_timezone(timezone)))
+            }
+            Self::Cron(cron) => cron.next_after(now),
+        }
+    }
+}
+
+/// `CLOCK_REALTIME` timer; unlike `tokio::time::sleep` it fires on time after a suspend, and
+/// wakes up early when the system clock is set.
+#[derive(Debug)]
+pub(super) struct WallClockTimer {
+    fd: AsyncFd<File>,
+}
+
+impl WallClockTimer {
+    fn new() -> io::Result<Self> {
+        // SAFETY: plain syscall, the returned descriptor is checked below.
+        let fd = unsafe {
+            libc::timerfd_create(libc::CLOCK_REALTIME, libc::TFD_NONBLOCK | libc::TFD_CLOEXEC)
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // SAFETY: `fd` is a freshly created descriptor owned by nobody else.
+        let file = unsafe { File::from_raw_fd(fd) };
+
+        Ok(Self {
+            fd: AsyncFd::new(file)?,
+        })
+    }
+
+    /// Sleeps until `deadline`, or until the system clock is set.
+    async fn sleep_until(&self, deadline: DateTime<Utc>) -> io::Result<()> {
+        let spec = libc::itimerspec {
+            it_interval: libc::timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            },
+            it_value: libc::timespec {
+                tv_sec: deadline.timestamp() as libc::time_t,
+                tv_nsec: deadline.timestamp_subsec_nanos() as libc::c_long,
+            },
+        };
+
+        // SAFETY: `spec` outlives the call and the old value is not requested.
+        let res = unsafe {
+            libc::timerfd_settime(
+                self.fd.as_raw_fd(),
+                libc::TFD_TIMER_ABSTIME | libc::TFD_TIMER_CANCEL_ON_SET,
+                &spec,
+                std::ptr::null_mut(),
+            )
+        };
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        loop {
+            let mut guard = self.fd.readable().await?;
+
+            let mut expirations = [0u8; 8];
+            match guard.try_io(|fd| fd.get_ref().read(&mut expirations)) {
+                Ok(Ok(_)) => return Ok(()),
+                Ok(Err(err)) if err.raw_os_error() == Some(libc::ECANCELED) => return Ok(()),
+                Ok(Err(err)) => return Err(err),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::TimeZone;
+    use chrono_tz::Tz;
+
+    use super::{Align, WallClockTimer, duration};
+
+    #[test]
+    fn parses_single_units() {
+        assert_eq!(duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(duration("2s").unwrap(), Duration::from_secs(2));
+        assert_eq!(duration("2m").unwrap(), Duration::from_secs(120));
+        assert_eq!(duration("1h").unwrap(), Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn parses_combined_units() {
+        assert_eq!(duration("1m30s").unwrap(), Duration::from_secs(90));
+        assert_eq!(duration("1s500ms").unwrap(), Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn zero_is_valid_syntax() {
+        assert_eq!(duration("0s").unwrap(), Duration::ZERO);
+    }
+
+    #[test]
+    fn errors_on_invalid_syntax() {
+        for spec in ["", "10", "s", "1d", "1.5s", "-1s", "1 s"] {
+            assert!(duration(spec).is_err(), "{spec}");
+        }
+    }
+
+    #[test]
+    fn derives_alignment_from_format() {
+        assert_eq!(Align::from_format("%H:%M:%S"), Align::Second);
+        assert_eq!(Align::from_format("%T"), Align::Second);
+        assert_eq!(Align::from_format("%H:%M"), Align::Minute);
+        assert_eq!(Align::from_format("%-I %p"), Align::Hour);
+        assert_eq!(Align::from_format("%d/%m %a"), Align::Day);
+        assert_eq!(Align::from_format("100%% done"), Align::Day);
+    }
+
+    #[test]
+    fn next_boundary_is_strictly_after_now() {
+        let now = Tz::UTC.with_ymd_and_hms(2024, 3, 5, 10, 59, 59).unwrap();
+        let at = |h, m, s| Tz::UTC.with_ymd_and_hms(2024, 3, 5, h, m, s).unwrap();
+
+        assert_eq!(Align::Second.next_boundary(now), at(11, 0, 0));
+        assert_eq!(Align::Minute.next_boundary(now), at(11, 0, 0));
+        assert_eq!(Align::Hour.next_boundary(at(11, 0, 0)), at(12, 0, 0));
+    }
+
+    #[test]
+    fn day_boundary_is_local_midnight() {
+        let vienna = chrono_tz::Europe::Vienna;
+        let now = vienna.with_ymd_and_hms(2024, 3, 5, 23, 30, 0).unwrap();
+        let midnight = vienna.with_ymd_and_hms(2024, 3, 6, 0, 0, 0).unwrap();
+
+        assert_eq!(Align::Day.next_boundary(now), midnight);
+    }
+
+    #[tokio::test]
+    async fn wall_clock_timer_fires_at_deadline() {
+        let timer = WallClockTimer::new().unwrap();
+        let deadline = chrono::Utc::now() + chrono::TimeDelta::milliseconds(50);
+
+        timer.sleep_until(deadline).await.unwrap();
+        assert!(chrono::Utc::now() >= deadline);
+    }
+}
@@ -0,0 +1,301 @@
+use std::fmt;
+
+use chrono::{DateTime, Datelike, NaiveDate, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::error::{Error, Result};
+
+/// Days searched for the next match, covering leap days and rare weekday/date combinations.
+const SEARCH_DAYS: u32 = 5 * 366;
+
+const MONTHS: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Five field cron expression (`minute hour day-of-month month day-of-week`), evaluated in
+/// `timezone`.
+///
+/// Fields take `*`, numbers, ranges `a-b`, steps `*/n` or `a-b/n` and lists separated by `,`;
+/// months and weekdays also take names like `jan` or `mon`, and Sunday is `0` or `7`. As in
+/// cron, a restricted day-of-month and day-of-week match when either of them matches.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Cron {
+    /// The expression as written, for display.
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// `days` and `weekdays` are both restricted.
+    either_day: bool,
+    timezone: Tz,
+}
+
+impl Cron {
+    pub fn parse(expression: &str, timezone: Tz) -> Result<Self> {
+        let invalid = |message: String| {
+            Error::config(format!("invalid cron expression `{expression}`: {message}"))
+        };
+
+        let fields = expression.split_whitespace().collect::<Vec<_>>();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(invalid(format!(
+                "expected 5 fields, found {}",
+                fields.len()
+            )));
+        };
+
+        let minutes = parse_field(minutes, 0, 59, &[]).map_err(invalid)?;
+        let hours = parse_field(hours, 0, 23, &[]).map_err(invalid)?;
+        let day_set = parse_field(days, 1, 31, &[]).map_err(invalid)?;
+        let months = parse_field(months, 1, 12, MONTHS).map_err(invalid)?;
+        let mut weekday_set = parse_field(weekdays, 0, 7, WEEKDAYS).map_err(invalid)?;
+
+        // Sunday is both `0` and `7`.
+        if weekday_set & 1 << 7 != 0 {
+            weekday_set |= 1;
+        }
+
+        Ok(Self {
+            expression: expression.to_owned(),
+            minutes,
+            hours,
+            days: day_set,
+            months,
+            weekdays: weekday_set,
+            either_day: !days.starts_with('*') && !weekdays.starts_with('*'),
+            timezone,
+        })
+    }
+
+    /// First matching minute strictly after `now`; `None` if nothing matches for years,
+    /// e.g. `0 0 30 2 *`.
+    pub fn next_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let now = now.with_timezone(&self.timezone);
+        let today = now.date_naive();
+
+        (0..SEARCH_DAYS)
+            .filter_map(|offset| today.checked_add_signed(TimeDelta::days(offset.into())))
+            .filter(|day| self.matches_day(*day))
+            .find_map(|day| {
+                (0..24u32)
+                    .filter(|hour| contains(self.hours, *hour))
+                    .flat_map(|hour| {
+                        (0..60u32)
+                            .filter(|minute| contains(self.minutes, *minute))
+                            .map(move |minute| (hour, minute))
+                    })
+                    .filter_map(|(hour, minute)| day.and_hms_opt(hour, minute, 0))
+                    // Local times skipped by a DST change do not occur.
+                    .filter_map(|local| self.timezone.from_local_datetime(&local).earliest())
+                    .find(|time| *time > now)
+            })
+            .map(|time| time.with_timezone(&Utc))
+    }
+
+    fn matches_day(&self, day: NaiveDate) -> bool {
+        if !contains(self.months, day.month()) {
+            return false;
+        }
+
+        let day_of_month = contains(self.days, day.day());
+        let weekday = contains(self.weekdays, day.weekday().num_days_from_sunday());
+
+        if self.either_day {
+            day_of_month || weekday
+        } else {
+            day_of_month && weekday
+        }
+    }
+}
+
+impl fmt::Debug for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Cron")
+            .field(&self.expression)
+            .field(&self.timezone)
+            .finish()
+    }
+}
+
+impl fmt::Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.expression, self.timezone)
+    }
+}
+
+const fn contains(set: u64, value: u32) -> bool {
+    set & 1 << value != 0
+}
+
+/// Bit set of the values of a single field within `min..=max`.
+fn parse_field(
+    field: &str,
+    min: u32,
+    max: u32,
+    names: &[&str],
+) -> std::result::Result<u64, String> {
+    let value = |value: &str| -> std::result::Result<u32, String> {
+        let parsed = match names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(value))
+        {
+            // Names count from the lowest value, `jan` is `1` and `sun` is `0`.
+            Some(position) => min + position as u32,
+            None => value
+                .parse()
+                .map_err(|_| format!("invalid value `{value}` in `{field}`"))?,
+        };
+
+        if !(min..=max).contains(&parsed) {
+            return Err(format!(
+                "`{value}` is out of range {min}-{max} in `{field}`"
+            ));
+        }
+
+        Ok(parsed)
+    };
+
+    let mut set = 0;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("invalid step `{step}` in `{field}`")),
+            },
+            None => (part, 1),
+        };
+
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (value(start)?, value(end)?),
+                // `5/15` runs from `5` to the end of the field.
+                None if step > 1 => (value(range)?, max),
+                None => {
+                    let value = value(range)?;
+                    (value, value)
+                }
+            },
+        };
+
+        if start > end {
+            return Err(format!("invalid range `{range}` in `{field}`"));
+        }
+
+        set |= (start..=end)
+            .step_by(step as usize)
+            .fold(0, |set, value| set | 1 << value);
+    }
+
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use chrono_tz::Tz;
+
+    use super::Cron;
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> chrono::DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    #[test]
+    fn runs_at_listed_minutes() {
+        let cron = Cron::parse("0,30 * * * *", Tz::UTC).unwrap();
+
+        assert_eq!(
+            cron.next_after(utc(2024, 3, 5, 10, 0)),
+            Some(utc(2024, 3, 5, 10, 30))
+        );
+        assert_eq!(
+            cron.next_after(utc(2024, 3, 5, 10, 45)),
+            Some(utc(2024, 3, 5, 11, 0))
+        );
+    }
+
+    #[test]
+    fn prints_the_expression() {
+        let cron = Cron::parse("0,30 * * * *", chrono_tz::Europe::Vienna).unwrap();
+
+        assert_eq!(
+            format!("{cron:?}"),
+            r#"Cron("0,30 * * * *", Europe/Vienna)"#
+        );
+        assert_eq!(cron.to_string(), "0,30 * * * * (Europe/Vienna)");
+    }
+
+    #[test]
+    fn runs_on_weekdays_only() {
+        let cron = Cron::parse("0 9 * * mon-fri", Tz::UTC).unwrap();
+
+        // Friday 2024-03-08 after 09:00 continues on Monday.
+        assert_eq!(
+            cron.next_after(utc(2024, 3, 8, 9, 0)),
+            Some(utc(2024, 3, 11, 9, 0))
+        );
+    }
+
+    #[test]
+    fn evaluates_in_timezone() {
+        let cron = Cron::parse("0 9 * * *", chrono_tz::Europe::Vienna).unwrap();
+
+        // 09:00 in Vienna is 08:00 UTC in winter.
+        assert_eq!(
+            cron.next_after(utc(2024, 1, 15, 12, 0)),
+            Some(utc(2024, 1, 16, 8, 0))
+        );
+    }
+
+    #[test]
+    fn restricted_day_and_weekday_match_either() {
+        let cron = Cron::parse("0 0 1 * sun", Tz::UTC).unwrap();
+
+        // Sunday 2024-03-10 comes before April 1st.
+        assert_eq!(
+            cron.next_after(utc(2024, 3, 5, 0, 0)),
+            Some(utc(2024, 3, 10, 0, 0))
+        );
+    }
+
+    #[test]
+    fn supports_steps_and_sunday_as_seven() {
+        let cron = Cron::parse("*/15 8-18/2 * * 7", Tz::UTC).unwrap();
+
+        // 2024-03-10 is a Sunday.
+        assert_eq!(
+            cron.next_after(utc(2024, 3, 10, 8, 50)),
+            Some(utc(2024, 3, 10, 10, 0))
+        );
+    }
+
+    #[test]
+    fn impossible_dates_never_match() {
+        let cron = Cron::parse("0 0 30 feb *", Tz::UTC).unwrap();
+        assert_eq!(cron.next_after(utc(2024, 1, 1, 0, 0)), None);
+    }
+
+    #[test]
+    fn errors_on_invalid_expressions() {
+        for expression in [
+            "* * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "a * * * *",
+        ] {
+            assert!(
+                Cron::parse(expression, Tz::UTC).is_err(),
+                "{expression} parsed"
+            );
+        }
+    }
+}
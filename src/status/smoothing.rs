@@ -0,0 +1,149 @@
+use std::collections::VecDeque;
+
+/// Smoothing of noisy numeric outputs, e.g. CPU usage.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Smoothing {
+    /// Exponential moving average; weight of the newest sample, in `(0, 1]`.
+    Ema(f64),
+    /// Mean of the last n samples.
+    Mean(usize),
+    /// Maximum of the last n samples.
+    Max(usize),
+}
+
+impl Smoothing {
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Self::Ema(weight) => weight > 0.0 && weight <= 1.0,
+            Self::Mean(samples) | Self::Max(samples) => samples > 0,
+        }
+    }
+}
+
+/// State of a `Smoothing` across runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Smoother {
+    smoothing: Smoothing,
+    samples: VecDeque<f64>,
+    average: Option<f64>,
+    /// Unsmoothed output of the last run, if it was a number.
+    raw: Option<String>,
+}
+
+impl Smoother {
+    pub fn new(smoothing: Smoothing) -> Self {
+        Self {
+            smoothing,
+            samples: VecDeque::new(),
+            average: None,
+            raw: None,
+        }
+    }
+
+    pub const fn smoothing(&self) -> Smoothing {
+        self.smoothing
+    }
+
+    pub fn raw(&self) -> Option<&str> {
+        self.raw.as_deref()
+    }
+
+    /// Smoothed `output`, with as many decimals as the output; non-numbers are kept as is.
+    pub fn smooth(&mut self, output: String) -> String {
+        let Ok(sample) = output.trim().parse::<f64>() else {
+            self.raw = None;
+            return output;
+        };
+
+        let smoothed = self.push(sample);
+        let decimals = output
+            .trim()
+            .split_once('.')
+            .map_or(0, |(_, decimals)| decimals.len());
+
+        self.raw = Some(output);
+        format!("{smoothed:.decimals$}")
+    }
+
+    /// Forgets the raw output after a failed run.
+    pub fn clear_raw(&mut self) {
+        self.raw = None;
+    }
+
+    fn push(&mut self, sample: f64) -> f64 {
+        match self.smoothing {
+            Smoothing::Ema(weight) => *self.average.insert(
+                self.average
+                    .map_or(sample, |average| average + weight * (sample - average)),
+            ),
+            Smoothing::Mean(length) | Smoothing::Max(length) => {
+                if self.samples.len() >= length {
+                    self.samples.pop_front();
+                }
+                self.samples.push_back(sample);
+
+                if let Smoothing::Max(_) = self.smoothing {
+                    self.samples.iter().copied().fold(f64::MIN, f64::max)
+                } else {
+                    self.samples.iter().sum::<f64>() / self.samples.len() as f64
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Smoother, Smoothing};
+
+    fn smooth(smoothing: Smoothing, outputs: &[&str]) -> Vec<String> {
+        let mut smoother = Smoother::new(smoothing);
+        outputs
+            .iter()
+            .map(|output| smoother.smooth(output.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn averages_exponentially() {
+        assert_eq!(
+            smooth(Smoothing::Ema(0.5), &["10", "20", "20"]),
+            ["10", "15", "18"]
+        );
+    }
+
+    #[test]
+    fn averages_over_window() {
+        assert_eq!(
+            smooth(Smoothing::Mean(2), &["1.0", "2.0", "4.0"]),
+            ["1.0", "1.5", "3.0"]
+        );
+    }
+
+    #[test]
+    fn takes_window_maximum() {
+        assert_eq!(
+            smooth(Smoothing::Max(2), &["5", "1", "2", "1"]),
+            ["5", "5", "2", "2"]
+        );
+    }
+
+    #[test]
+    fn keeps_raw_value_and_non_numbers() {
+        let mut smoother = Smoother::new(Smoothing::Mean(2));
+        smoother.smooth("10".to_string());
+        assert_eq!(smoother.smooth("20".to_string()), "15");
+        assert_eq!(smoother.raw(), Some("20"));
+
+        assert_eq!(smoother.smooth("muted".to_string()), "muted");
+        assert_eq!(smoother.raw(), None);
+    }
+
+    #[test]
+    fn validates_parameters() {
+        assert!(Smoothing::Ema(0.3).is_valid());
+        assert!(!Smoothing::Ema(0.0).is_valid());
+        assert!(!Smoothing::Ema(1.5).is_valid());
+        assert!(!Smoothing::Mean(0).is_valid());
+    }
+}
@@ -1,71 +1,211 @@
+use std::{fmt, future::pending, time::Duration};
+
 use chrono::Utc;
 use chrono_tz::Tz;
+use futures::future::LocalBoxFuture;
+
+pub use external::Inbox;
 
 use super::utils::read_line;
-use crate::status::Result;
+use crate::status::{Error, Result};
 
 mod command;
 mod cpu;
+mod external;
+mod keyboard;
+mod mpd;
 mod ram;
+mod volume;
+mod window;
+
+/// Value shown by a `Status`; implemented by the `BuiltIn` sources and by custom ones.
+///
+/// Futures are boxed to keep the trait object safe, e.g.
+/// `Box::pin(async move { Ok(self.count.to_string()) })`.
+pub trait Source: fmt::Debug {
+    /// Name of the source in logs, e.g. ``battery `BAT0` ``.
+    fn label(&self) -> String;
+
+    /// Fetches the current value.
+    fn output(&mut self) -> LocalBoxFuture<'_, Result<String>>;
+
+    /// Resolves when an event-driven source has changed and should be refreshed early;
+    /// never resolves for polled sources.
+    fn changed(&mut self) -> LocalBoxFuture<'_, Result<()>> {
+        Box::pin(pending())
+    }
+
+    /// Chrono format and timezone of clock sources, used by aligned schedules.
+    fn clock(&self) -> Option<(&str, Tz)> {
+        None
+    }
+
+    /// Name and sender of push-based sources, whose values are set over the control socket.
+    fn inbox(&self) -> Option<(&str, Inbox)> {
+        None
+    }
+
+    /// Checks the source settings, returning `Error::Config` for invalid ones.
+    fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+}
 
 #[derive(Debug)]
-pub enum Source {
+pub enum BuiltIn {
     Command {
         cmd: &'static str,
         args: &'static [&'static str],
-        /// Timeout in seconds for the spawned process; on timeout returns `err`.
-        timeout: u64,
+        /// Timeout for the spawned process; on timeout returns `err`.
+        timeout: Duration,
     },
     Shell {
         script: &'static str,
-        /// Timeout in seconds for the spawned process; on timeout returns `err`.
-        timeout: u64,
+        /// Timeout for the spawned process; on timeout returns `err`.
+        timeout: Duration,
     },
     Cpu(cpu::Cpu),
     Battery {
         name: &'static str,
     },
     Ram,
+    Volume(volume::Volume),
+    Mpd(mpd::Mpd),
+    Keyboard(keyboard::Keyboard),
+    ActiveWindow(window::ActiveWindow),
     DateTime {
         format: &'static str,
         timezone: Tz,
     },
+    External(external::External),
 }
 
-impl Source {
+impl BuiltIn {
     pub fn cpu() -> Self {
         Self::Cpu(cpu::Cpu::default())
     }
 
-    pub fn label(&self) -> String {
-        match self {
-            Self::Command { .. } => "command".to_string(),
-            Self::Shell { .. } => "shell".to_string(),
-            Self::Cpu(_) => "cpu".to_string(),
-            Self::Battery { name } => format!("battery `{name}`"),
-            Self::Ram => "ram".to_string(),
-            Self::DateTime { format, .. } => format!("datetime `{format}`"),
-        }
+    /// ALSA mixer `control` (e.g. `Master`) on sound card number `card`; shows `muted` while muted.
+    pub fn volume(card: u32, control: &'static str, muted: &'static str) -> Self {
+        Self::Volume(volume::Volume::new(card, control, muted))
+    }
+
+    /// MPD server at `address` (`host:port` or a unix socket path); `format` takes
+    /// `{artist}`, `{title}`, `{album}`, `{file}`, `{state}`, `{elapsed}`, `{duration}`
+    /// and `{volume}`. Outputs nothing while stopped.
+    pub const fn mpd(address: &'static str, format: &'static str) -> Self {
+        Self::Mpd(mpd::Mpd::new(address, format))
+    }
+
+    /// Current XKB keyboard layout, e.g. `us` or `de`.
+    pub fn keyboard() -> Self {
+        Self::Keyboard(keyboard::Keyboard::default())
+    }
+
+    /// Title of the focused window (`_NET_ACTIVE_WINDOW`), cut to `max_length` cells.
+    pub const fn active_window(max_length: usize) -> Self {
+        Self::ActiveWindow(window::ActiveWindow::new(max_length))
+    }
+
+    /// Value pushed over the control socket with `set <name> <text>`, or written as lines to the
+    /// named pipe `fifo` (created if missing). Reverts to the status default after `ttl`, or when
+    /// an empty value is pushed.
+    pub fn external(name: &'static str, fifo: Option<&'static str>, ttl: Option<Duration>) -> Self {
+        Self::External(external::External::new(name, fifo, ttl))
     }
-}
 
-impl Source {
     pub async fn output(&mut self) -> Result<String> {
         match self {
             Self::Command { cmd, args, timeout } => command::run(cmd, args, *timeout).await,
-            Self::Shell {
-                script,
-                timeout: timeout_secs,
-            } => command::run("sh", &["-c", script], *timeout_secs).await,
+            Self::Shell { script, timeout } => command::run("sh", &["-c", script], *timeout).await,
             Self::Cpu(cpu) => cpu.cpu_percent().await,
             Self::Battery { name } => {
                 read_line(&format!("/sys/class/power_supply/{name}/capacity")).await
             }
             Self::Ram => ram::ram_percent().await,
+            Self::Volume(volume) => volume.output(),
+            Self::Mpd(mpd) => mpd.output().await,
+            Self::Keyboard(keyboard) => keyboard.output(),
+            Self::ActiveWindow(window) => window.output(),
             Self::DateTime { format, timezone } => Ok(Utc::now()
                 .with_timezone(timezone)
                 .format(format)
                 .to_string()),
+            Self::External(external) => external.output(),
+        }
+    }
+
+    pub async fn changed(&mut self) -> Result<()> {
+        match self {
+            Self::Volume(volume) => volume.changed().await,
+            Self::Mpd(mpd) => mpd.changed().await,
+            Self::Keyboard(keyboard) => keyboard.changed().await,
+            Self::ActiveWindow(window) => window.changed().await,
+            Self::External(external) => external.changed().await,
+            _ => pending().await,
+        }
+    }
+}
+
+impl Source for BuiltIn {
+    fn label(&self) -> String {
+        match self {
+            Self::Command { .. } => "command".to_string(),
+            Self::Shell { .. } => "shell".to_string(),
+            Self::Cpu(_) => "cpu".to_string(),
+            Self::Battery { name } => format!("battery `{name}`"),
+            Self::Ram => "ram".to_string(),
+            Self::Volume(volume) => format!("volume `{}`", volume.control()),
+            Self::Mpd(mpd) => format!("mpd `{}`", mpd.address()),
+            Self::Keyboard(_) => "keyboard".to_string(),
+            Self::ActiveWindow(_) => "active window".to_string(),
+            Self::DateTime { format, .. } => format!("datetime `{format}`"),
+            Self::External(external) => format!("external `{}`", external.name()),
+        }
+    }
+
+    fn output(&mut self) -> LocalBoxFuture<'_, Result<String>> {
+        Box::pin(BuiltIn::output(self))
+    }
+
+    fn changed(&mut self) -> LocalBoxFuture<'_, Result<()>> {
+        Box::pin(BuiltIn::changed(self))
+    }
+
+    fn clock(&self) -> Option<(&str, Tz)> {
+        match self {
+            Self::DateTime { format, timezone } => Some((format, *timezone)),
+            _ => None,
+        }
+    }
+
+    fn inbox(&self) -> Option<(&str, Inbox)> {
+        match self {
+            Self::External(external) => Some((external.name(), external.inbox())),
+            _ => None,
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self {
+            Self::Command { timeout, .. } | Self::Shell { timeout, .. } if timeout.is_zero() => {
+                Err(Error::config(format!(
+                    "{} `timeout` cannot be `0`",
+                    self.label()
+                )))
+            }
+            Self::External(external)
+                if external.name().is_empty() || external.name().contains(char::is_whitespace) =>
+            {
+                Err(Error::config(format!(
+                    "{} name must be a single word",
+                    self.label()
+                )))
+            }
+            Self::External(external) if external.ttl().is_some_and(|ttl| ttl.is_zero()) => Err(
+                Error::config(format!("{} `ttl` cannot be `0`", self.label())),
+            ),
+            _ => Ok(()),
         }
     }
 }
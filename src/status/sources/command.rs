@@ -2,8 +2,8 @@ use std::time::Duration;
 
 use crate::status::{Error, Result};
 
-/// Runs a command with a per-run timeout in seconds.
-pub async fn run(cmd: &str, args: &[&str], timeout: u64) -> Result<String> {
+/// Runs a command with a per-run timeout.
+pub async fn run(cmd: &str, args: &[&str], timeout: Duration) -> Result<String> {
     let mut cmd_tokio = tokio::process::Command::new(cmd);
     cmd_tokio.kill_on_drop(true).args(args);
 
@@ -13,7 +13,7 @@ pub async fn run(cmd: &str, args: &[&str], timeout: u64) -> Result<String> {
         format!("{} {}", cmd, args.join(" "))
     };
 
-    match tokio::time::timeout(Duration::from_secs(timeout), cmd_tokio.output()).await {
+    match tokio::time::timeout(timeout, cmd_tokio.output()).await {
         Ok(Ok(res)) => {
             if !res.status.success() {
                 let stderr = String::from_utf8_lossy(&res.stderr);
@@ -0,0 +1,275 @@
+use std::{ffi::CString, fmt, future::pending, io, os::unix::ffi::OsStrExt, path::Path};
+
+use tokio::{
+    io::{AsyncBufReadExt, BufReader, Lines},
+    net::unix::pipe,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use crate::status::{Error, Result};
+
+/// Sends values to an `External` source; an empty value reverts it to the status default.
+pub type Inbox = mpsc::UnboundedSender<String>;
+
+/// Wait before opening the named pipe again after it failed, doubled on every failure.
+const FIFO_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_FIFO_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+enum Fifo {
+    None,
+    /// Opened on the first wait for changes, and again at `retry_at` after opening or reading
+    /// failed, e.g. because its directory did not exist yet.
+    Closed {
+        path: &'static str,
+        retry_at: Instant,
+        delay: Duration,
+    },
+    Open {
+        path: &'static str,
+        lines: Lines<BufReader<pipe::Receiver>>,
+    },
+}
+
+impl Fifo {
+    fn closed(path: &'static str, retry_at: Instant, delay: Duration) -> Self {
+        Self::Closed {
+            path,
+            retry_at,
+            delay: delay.min(MAX_FIFO_RETRY_DELAY),
+        }
+    }
+}
+
+/// Value pushed by other programs, over the control socket or a named pipe.
+pub struct External {
+    name: &'static str,
+    /// Pushed values revert to the status default after this long.
+    ttl: Option<Duration>,
+    fifo: Fifo,
+    inbox: Inbox,
+    values: mpsc::UnboundedReceiver<String>,
+    /// Current value and when it was pushed.
+    value: Option<(String, Instant)>,
+}
+
+impl fmt::Debug for External {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("External")
+            .field("name", &self.name)
+            .field("ttl", &self.ttl)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+impl External {
+    pub fn new(name: &'static str, fifo: Option<&'static str>, ttl: Option<Duration>) -> Self {
+        let (inbox, values) = mpsc::unbounded_channel();
+
+        Self {
+            name,
+            ttl,
+            fifo: fifo.map_or(Fifo::None, |path| {
+                Fifo::closed(path, Instant::now(), FIFO_RETRY_DELAY)
+            }),
+            inbox,
+            values,
+            value: None,
+        }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub const fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    pub fn inbox(&self) -> Inbox {
+        self.inbox.clone()
+    }
+
+    /// The pushed value, empty if none is set or it expired.
+    pub fn output(&mut self) -> Result<String> {
+        if self.expires_at().is_some_and(|at| at <= Instant::now()) {
+            self.value = None;
+        }
+
+        Ok(self
+            .value
+            .as_ref()
+            .map_or_else(String::new, |(value, _)| value.clone()))
+    }
+
+    /// Waits for a pushed value, or for the current one to expire.
+    pub async fn changed(&mut self) -> Result<()> {
+        loop {
+            if let Fifo::Closed {
+                path,
+                retry_at,
+                delay,
+            } = self.fifo
+                && retry_at <= Instant::now()
+            {
+                match open_fifo(Path::new(path)) {
+                    Ok(fifo) => {
+                        self.fifo = Fifo::Open {
+                            path,
+                            lines: BufReader::new(fifo).lines(),
+                        };
+                    }
+                    Err(err) => {
+                        self.fifo = Fifo::closed(path, Instant::now() + delay, delay * 2);
+                        return Err(Error::io(path, err));
+                    }
+                }
+            }
+
+            let expires_at = self.expires_at();
+            let retry_at = match self.fifo {
+                Fifo::Closed { retry_at, .. } => Some(retry_at),
+                _ => None,
+            };
+            let value = tokio::select! {
+                Some(value) = self.values.recv() => value,
+                line = next_line(&mut self.fifo) => match line {
+                    Ok(value) => value,
+                    Err(err) => {
+                        if let Fifo::Open { path, .. } = self.fifo {
+                            self.fifo = Fifo::closed(
+                                path,
+                                Instant::now() + FIFO_RETRY_DELAY,
+                                FIFO_RETRY_DELAY * 2,
+                            );
+                        }
+                        return Err(Error::io(self.name, err));
+                    }
+                },
+                () = sleep_until(expires_at) => String::new(),
+                () = sleep_until(retry_at) => continue,
+            };
+
+            self.value = (!value.is_empty()).then(|| (value, Instant::now()));
+            return Ok(());
+        }
+    }
+
+    fn expires_at(&self) -> Option<Instant> {
+        let (_, pushed_at) = self.value.as_ref()?;
+        Some(*pushed_at + self.ttl?)
+    }
+}
+
+/// Opens `path` for reading, creating the named pipe first if needed.
+///
+/// Opened for writing too, so the pipe does not reach end-of-file when a writer closes it.
+fn open_fifo(path: &Path) -> io::Result<pipe::Receiver> {
+    if !path.exists() {
+        let c_path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        // SAFETY: `c_path` is a valid NUL-terminated string that outlives the call.
+        if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    pipe::OpenOptions::new()
+        .read_write(true)
+        .open_receiver(path)
+}
+
+/// Next line written to the pipe; never resolves without an open pipe.
+async fn next_line(fifo: &mut Fifo) -> io::Result<String> {
+    match fifo {
+        Fifo::Open { lines, .. } => match lines.next_line().await? {
+            Some(line) => Ok(line.trim_end().to_string()),
+            None => pending().await,
+        },
+        _ => pending().await,
+    }
+}
+
+/// Completes at `at`, never without one.
+async fn sleep_until(at: Option<Instant>) {
+    match at {
+        Some(at) => tokio::time::sleep_until(at).await,
+        None => pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::AsyncWriteExt,
+        time::{Duration, Instant},
+    };
+
+    use super::External;
+
+    #[tokio::test]
+    async fn shows_pushed_values_until_they_expire() {
+        let mut external = External::new("ci", None, Some(Duration::from_millis(50)));
+        assert_eq!(external.output().unwrap(), "");
+
+        external.inbox().send(String::from("passed")).unwrap();
+        external.changed().await.unwrap();
+        assert_eq!(external.output().unwrap(), "passed");
+
+        let pushed = Instant::now();
+        external.changed().await.unwrap();
+        assert!(pushed.elapsed() >= Duration::from_millis(40));
+        assert_eq!(external.output().unwrap(), "");
+    }
+
+    #[tokio::test]
+    async fn reads_lines_from_fifo() {
+        let path = std::env::temp_dir().join(format!("stsr-fifo-{}", std::process::id()));
+        let path: &'static str = String::leak(path.to_string_lossy().into_owned());
+        let mut external = External::new("pomodoro", Some(path), None);
+
+        let writer = async {
+            // Wait until `changed` created the pipe.
+            while !std::path::Path::new(path).exists() {
+                tokio::task::yield_now().await;
+            }
+            let mut sender = tokio::net::unix::pipe::OpenOptions::new()
+                .open_sender(path)
+                .unwrap();
+            sender.write_all(b"25:00\n").await.unwrap();
+        };
+        let (changed, ()) = tokio::join!(external.changed(), writer);
+        changed.unwrap();
+        assert_eq!(external.output().unwrap(), "25:00");
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn retries_opening_fifo() {
+        let dir = std::env::temp_dir().join(format!("stsr-fifo-dir-{}", std::process::id()));
+        let path = dir.join("fifo");
+        let path: &'static str = String::leak(path.to_string_lossy().into_owned());
+        let mut external = External::new("pomodoro", Some(path), None);
+
+        // The directory does not exist yet.
+        assert!(external.changed().await.is_err());
+        std::fs::create_dir(&dir).unwrap();
+
+        let writer = async {
+            while !std::path::Path::new(path).exists() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            let mut sender = tokio::net::unix::pipe::OpenOptions::new()
+                .open_sender(path)
+                .unwrap();
+            sender.write_all(b"break\n").await.unwrap();
+        };
+        let (changed, ()) = tokio::join!(external.changed(), writer);
+        changed.unwrap();
+        assert_eq!(external.output().unwrap(), "break");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,211 @@
+use std::future::pending;
+
+use x11rb::protocol::{
+    Event,
+    xkb::{self, ConnectionExt as _},
+    xproto::ConnectionExt as _,
+};
+
+use crate::{
+    status::{Error, Result},
+    x11::EventConnection,
+};
+
+/// Symbols that are options rather than layouts, e.g. `pc` and `inet` in `pc+us+de:2+inet(evdev)`.
+const NON_LAYOUT_SYMBOLS: &[&str] = &[
+    "altwin",
+    "capslock",
+    "compose",
+    "ctrl",
+    "eurosign",
+    "group",
+    "inet",
+    "japan",
+    "keypad",
+    "korean",
+    "kpdl",
+    "level3",
+    "level5",
+    "lv3",
+    "lv5",
+    "nbsp",
+    "pc",
+    "shift",
+    "srvr_ctrl",
+    "terminate",
+];
+
+/// Current XKB layout, refreshed on `XkbStateNotify` and `XkbNamesNotify` events.
+#[derive(Debug, Default)]
+pub struct Keyboard {
+    /// Dropped on errors, so the next output reconnects.
+    connection: Option<Box<EventConnection>>,
+}
+
+impl Keyboard {
+    pub fn output(&mut self) -> Result<String> {
+        let connection = match self.connection.take() {
+            Some(connection) => connection,
+            None => Box::new(connect()?),
+        };
+
+        let layout = current_layout(&connection)?;
+        self.connection = Some(connection);
+
+        Ok(layout)
+    }
+
+    pub async fn changed(&mut self) -> Result<()> {
+        let Some(connection) = &self.connection else {
+            return pending().await;
+        };
+
+        let changed = loop {
+            match connection.next_event().await {
+                Ok(Event::XkbStateNotify(_) | Event::XkbNamesNotify(_)) => break Ok(()),
+                Ok(_) => continue,
+                Err(err) => break Err(err),
+            }
+        };
+
+        if changed.is_err() {
+            self.connection = None;
+        }
+
+        changed
+    }
+}
+
+fn connect() -> Result<EventConnection> {
+    let connection = EventConnection::connect()?;
+    let conn = connection.connection();
+
+    let supported = conn
+        .xkb_use_extension(1, 0)
+        .map_err(|err| Error::x11(format!("xkb use extension: {err}")))?
+        .reply()
+        .map_err(|err| Error::x11(format!("xkb use extension reply: {err}")))?
+        .supported;
+    if !supported {
+        return Err(Error::x11("xkb extension is not supported"));
+    }
+
+    let details = xkb::SelectEventsAux {
+        state_notify: Some(xkb::SelectEventsAuxStateNotify {
+            affect_state: xkb::StatePart::GROUP_STATE,
+            state_details: xkb::StatePart::GROUP_STATE,
+        }),
+        names_notify: Some(xkb::SelectEventsAuxNamesNotify {
+            affect_names: xkb::NameDetail::SYMBOLS,
+            names_details: xkb::NameDetail::SYMBOLS,
+        }),
+        ..Default::default()
+    };
+    conn.xkb_select_events(
+        xkb::ID::USE_CORE_KBD.into(),
+        0u16.into(),
+        0u16.into(),
+        0u16.into(),
+        0u16.into(),
+        &details,
+    )
+    .map_err(|err| Error::x11(format!("xkb select events: {err}")))?
+    .check()
+    .map_err(|err| Error::x11(format!("xkb select events reply: {err}")))?;
+
+    Ok(connection)
+}
+
+fn current_layout(connection: &EventConnection) -> Result<String> {
+    let conn = connection.connection();
+
+    let group = conn
+        .xkb_get_state(xkb::ID::USE_CORE_KBD.into())
+        .map_err(|err| Error::x11(format!("xkb get state: {err}")))?
+        .reply()
+        .map_err(|err| Error::x11(format!("xkb get state reply: {err}")))?
+        .group;
+
+    let symbols_atom = conn
+        .xkb_get_names(xkb::ID::USE_CORE_KBD.into(), xkb::NameDetail::SYMBOLS)
+        .map_err(|err| Error::x11(format!("xkb get names: {err}")))?
+        .reply()
+        .map_err(|err| Error::x11(format!("xkb get names reply: {err}")))?
+        .value_list
+        .symbols_name
+        .ok_or_else(|| Error::x11("xkb get names: missing symbols name"))?;
+
+    let symbols = conn
+        .get_atom_name(symbols_atom)
+        .map_err(|err| Error::x11(format!("get atom name: {err}")))?
+        .reply()
+        .map_err(|err| Error::x11(format!("get atom name reply: {err}")))?
+        .name;
+    let symbols = String::from_utf8_lossy(&symbols);
+
+    let group = usize::from(u8::from(group));
+    layouts(&symbols).into_iter().nth(group).ok_or_else(|| {
+        Error::parse(
+            "xkb symbols",
+            format!("no layout for group {group} in `{symbols}`"),
+        )
+    })
+}
+
+/// Layout names ordered by group, e.g. `["us", "de"]` for `pc+us+de:2+inet(evdev)`.
+fn layouts(symbols: &str) -> Vec<String> {
+    let mut layouts = symbols
+        .split('+')
+        .filter_map(|symbol| {
+            let (symbol, group) = match symbol.split_once(':') {
+                Some((symbol, group)) => (symbol, group.parse::<usize>().ok()),
+                None => (symbol, None),
+            };
+            let name = symbol.split('(').next().unwrap_or(symbol).trim();
+
+            (!name.is_empty() && !NON_LAYOUT_SYMBOLS.contains(&name)).then_some((group, name))
+        })
+        .enumerate()
+        .map(|(position, (group, name))| {
+            (
+                group.map_or(position, |group| group.saturating_sub(1)),
+                name,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    layouts.sort_by_key(|(group, _)| *group);
+
+    layouts
+        .into_iter()
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::layouts;
+
+    #[test]
+    fn parses_layouts_in_group_order() {
+        assert_eq!(
+            layouts("pc+us+de:2+inet(evdev)+group(alt_shift_toggle)"),
+            ["us", "de"]
+        );
+    }
+
+    #[test]
+    fn strips_variants() {
+        assert_eq!(layouts("pc+us(dvorak)+ru:2+inet(evdev)"), ["us", "ru"]);
+    }
+
+    #[test]
+    fn orders_by_explicit_group_index() {
+        assert_eq!(layouts("pc+de:2+us:1"), ["us", "de"]);
+    }
+
+    #[test]
+    fn empty_symbols_have_no_layouts() {
+        assert!(layouts("").is_empty());
+    }
+}
@@ -0,0 +1,367 @@
+use std::{fmt, future::pending, io, time::Duration};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::{TcpStream, UnixStream},
+};
+
+use crate::status::{Error, Result, utils::fill_template};
+
+/// Subsystems that trigger a refresh when changed.
+const IDLE_COMMAND: &[u8] = b"idle player mixer\n";
+/// Limit for connecting and reading the responses of one output, so a hung server cannot block
+/// the status.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+type Pairs = Vec<(String, String)>;
+
+pub struct Mpd {
+    /// `host:port`, or the path of a unix socket when starting with `/`.
+    address: &'static str,
+    /// Fields: `{artist}`, `{title}`, `{album}`, `{file}`, `{state}`, `{elapsed}`,
+    /// `{duration}` and `{volume}`.
+    format: &'static str,
+    timeout: Duration,
+    /// Dropped on errors, so the next output reconnects.
+    connection: Option<Connection>,
+}
+
+impl fmt::Debug for Mpd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mpd")
+            .field("address", &self.address)
+            .field("format", &self.format)
+            .field("connected", &self.connection.is_some())
+            .finish()
+    }
+}
+
+impl Mpd {
+    pub const fn new(address: &'static str, format: &'static str) -> Self {
+        Self {
+            address,
+            format,
+            timeout: TIMEOUT,
+            connection: None,
+        }
+    }
+
+    pub const fn address(&self) -> &str {
+        self.address
+    }
+
+    pub async fn output(&mut self) -> Result<String> {
+        let output = match tokio::time::timeout(self.timeout, self.query()).await {
+            Ok(output) => output.map_err(|err| Error::io(self.address, err)),
+            Err(_) => Err(Error::CommandTimeout {
+                command: format!("mpd `{}`", self.address),
+                timeout: self.timeout,
+            }),
+        };
+
+        if output.is_err() {
+            self.connection = None;
+        }
+
+        output
+    }
+
+    async fn query(&mut self) -> io::Result<String> {
+        let connection = match self.connection.take() {
+            Some(connection) => connection,
+            None => Connection::connect(self.address).await?,
+        };
+        let connection = self.connection.insert(connection);
+
+        if connection.idle {
+            connection.writer.write_all(b"noidle\n").await?;
+            connection.read_response().await?;
+            connection.idle = false;
+        }
+
+        let status = connection.command("status").await?;
+        let song = connection.command("currentsong").await?;
+
+        Ok(render(self.format, &status, &song))
+    }
+
+    /// Waits for a player or mixer change using the MPD `idle` command.
+    pub async fn changed(&mut self) -> Result<()> {
+        let Some(connection) = self.connection.as_mut() else {
+            return pending().await;
+        };
+
+        let changed = async {
+            if !connection.idle {
+                connection.writer.write_all(IDLE_COMMAND).await?;
+                connection.idle = true;
+            }
+
+            connection.read_response().await?;
+            connection.idle = false;
+
+            Ok::<_, io::Error>(())
+        }
+        .await;
+
+        if let Err(err) = changed {
+            self.connection = None;
+            return Err(Error::io(self.address, err));
+        }
+
+        Ok(())
+    }
+}
+
+struct Connection {
+    reader: BufReader<Box<dyn AsyncRead + Unpin>>,
+    writer: Box<dyn AsyncWrite + Unpin>,
+    /// Partially read line; kept across cancelled reads so no data is lost.
+    line: Vec<u8>,
+    /// An `idle` command was sent and its response is not fully read yet.
+    idle: bool,
+}
+
+impl Connection {
+    async fn connect(address: &str) -> io::Result<Self> {
+        let (reader, writer): (Box<dyn AsyncRead + Unpin>, Box<dyn AsyncWrite + Unpin>) =
+            if address.starts_with('/') {
+                let (reader, writer) = UnixStream::connect(address).await?.into_split();
+                (Box::new(reader), Box::new(writer))
+            } else {
+                let (reader, writer) = TcpStream::connect(address).await?.into_split();
+                (Box::new(reader), Box::new(writer))
+            };
+
+        let mut connection = Self {
+            reader: BufReader::new(reader),
+            writer,
+            line: Vec::new(),
+            idle: false,
+        };
+
+        let greeting = connection.read_line().await?;
+        if !greeting.starts_with("OK MPD") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unexpected greeting `{greeting}`"),
+            ));
+        }
+
+        Ok(connection)
+    }
+
+    async fn command(&mut self, command: &str) -> io::Result<Pairs> {
+        self.writer
+            .write_all(format!("{command}\n").as_bytes())
+            .await?;
+        self.read_response().await
+    }
+
+    async fn read_response(&mut self) -> io::Result<Pairs> {
+        let mut pairs = Pairs::new();
+
+        loop {
+            let line = self.read_line().await?;
+
+            if line == "OK" {
+                return Ok(pairs);
+            }
+
+            if let Some(ack) = line.strip_prefix("ACK ") {
+                return Err(io::Error::other(ack.to_string()));
+            }
+
+            if let Some((key, value)) = line.split_once(": ") {
+                pairs.push((key.to_string(), value.to_string()));
+            }
+        }
+    }
+
+    async fn read_line(&mut self) -> io::Result<String> {
+        self.reader.read_until(b'\n', &mut self.line).await?;
+
+        if self.line.pop() != Some(b'\n') {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        String::from_utf8(std::mem::take(&mut self.line))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+fn lookup<'a>(pairs: &'a Pairs, key: &str) -> Option<&'a str> {
+    pairs
+        .iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Renders `format` from `status` and `currentsong` responses; empty while stopped.
+fn render(format: &str, status: &Pairs, song: &Pairs) -> String {
+    let state = lookup(status, "state").unwrap_or("stop");
+    if state == "stop" {
+        return String::new();
+    }
+
+    let file = lookup(song, "file").unwrap_or_default();
+
+    fill_template(format, |field| {
+        let value = match field {
+            "artist" => lookup(song, "Artist").unwrap_or_default().to_string(),
+            "title" => lookup(song, "Title")
+                .unwrap_or_else(|| file.rsplit('/').next().unwrap_or(file))
+                .to_string(),
+            "album" => lookup(song, "Album").unwrap_or_default().to_string(),
+            "file" => file.to_string(),
+            "state" => state.to_string(),
+            "elapsed" => lookup(status, "elapsed")
+                .map(format_time)
+                .unwrap_or_default(),
+            "duration" => lookup(status, "duration")
+                .map(format_time)
+                .unwrap_or_default(),
+            "volume" => lookup(status, "volume").unwrap_or_default().to_string(),
+            _ => return None,
+        };
+
+        Some(value)
+    })
+}
+
+/// Formats fractional seconds as `m:ss`, or `h:mm:ss` from one hour on.
+fn format_time(seconds: &str) -> String {
+    let total = seconds.parse::<f64>().unwrap_or_default().max(0.0) as u64;
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{SinkExt, StreamExt, channel::mpsc};
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    const STATUS: &str = "volume: 80\nstate: play\nelapsed: 65.2\nduration: 3700.0\nOK\n";
+    const SONG: &str = "file: music/song.flac\nArtist: Artist\nTitle: Title\nOK\n";
+
+    /// Fake MPD server answering `status` and `currentsong`; `idle` returns once a
+    /// message is sent on the returned channel. Each connection is closed after
+    /// `commands_per_connection` commands.
+    async fn fake_mpd(commands_per_connection: usize) -> (&'static str, mpsc::Sender<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string().leak();
+        let (notify, mut changes) = mpsc::channel(1);
+
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let (reader, mut writer) = stream.into_split();
+                let mut lines = BufReader::new(reader).lines();
+                writer.write_all(b"OK MPD 0.23.5\n").await.unwrap();
+
+                for _ in 0..commands_per_connection {
+                    let Ok(Some(command)) = lines.next_line().await else {
+                        break;
+                    };
+
+                    let response = match command.as_str() {
+                        "status" => STATUS,
+                        "currentsong" => SONG,
+                        "idle player mixer" => tokio::select! {
+                            _ = changes.next() => "changed: player\nOK\n",
+                            Ok(Some(_noidle)) = lines.next_line() => "OK\n",
+                        },
+                        _ => "ACK [5@0] {} unknown command\n",
+                    };
+                    writer.write_all(response.as_bytes()).await.unwrap();
+                }
+            }
+        });
+
+        (address, notify)
+    }
+
+    #[tokio::test]
+    async fn renders_fields_from_server() {
+        let (address, _notify) = fake_mpd(usize::MAX).await;
+        let mut mpd = Mpd::new(address, "{artist} - {title} [{elapsed}/{duration}] {state}");
+
+        assert_eq!(
+            mpd.output().await.unwrap(),
+            "Artist - Title [1:05/1:01:40] play"
+        );
+    }
+
+    #[tokio::test]
+    async fn changed_resolves_on_idle_event_and_noidle_cancels() {
+        let (address, mut notify) = fake_mpd(usize::MAX).await;
+        let mut mpd = Mpd::new(address, "{title}");
+        mpd.output().await.unwrap();
+
+        notify.send(()).await.unwrap();
+        mpd.changed().await.unwrap();
+
+        // Cancel a pending `idle`, the next output has to send `noidle` first.
+        tokio::select! {
+            _ = mpd.changed() => panic!("no change was sent"),
+            () = tokio::task::yield_now() => {}
+        }
+        assert_eq!(mpd.output().await.unwrap(), "Title");
+    }
+
+    #[tokio::test]
+    async fn reconnects_after_connection_loss() {
+        let (address, _notify) = fake_mpd(2).await;
+        let mut mpd = Mpd::new(address, "{title}");
+
+        assert_eq!(mpd.output().await.unwrap(), "Title");
+        assert!(mpd.output().await.is_err());
+        assert_eq!(mpd.output().await.unwrap(), "Title");
+    }
+
+    #[tokio::test]
+    async fn times_out_on_hung_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string().leak();
+        // Accepts connections without ever sending the greeting.
+        let server = tokio::spawn(async move {
+            let mut streams = Vec::new();
+            loop {
+                streams.push(listener.accept().await.unwrap());
+            }
+        });
+
+        let mut mpd = Mpd {
+            timeout: Duration::from_millis(50),
+            ..Mpd::new(address, "{title}")
+        };
+        let err = mpd.output().await.unwrap_err();
+        assert_eq!(err.kind(), crate::error::ErrorKind::CommandTimeout);
+        assert!(mpd.connection.is_none());
+
+        server.abort();
+    }
+
+    #[test]
+    fn renders_empty_while_stopped() {
+        let status = vec![("state".to_string(), "stop".to_string())];
+        assert_eq!(render("{title}", &status, &Pairs::new()), "");
+    }
+
+    #[test]
+    fn title_falls_back_to_file_name() {
+        let status = vec![("state".to_string(), "pause".to_string())];
+        let song = vec![("file".to_string(), "dir/track.mp3".to_string())];
+        assert_eq!(render("{title} {state}", &status, &song), "track.mp3 pause");
+    }
+}
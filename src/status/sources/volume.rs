@@ -0,0 +1,405 @@
+use std::{
+    ffi::{c_int, c_long},
+    fs::File,
+    future::pending,
+    io::{self, Read},
+    os::{fd::AsRawFd, unix::fs::OpenOptionsExt},
+};
+
+use tokio::io::unix::AsyncFd;
+
+use crate::status::{Error, Result, utils::rounded_percent};
+
+// Subset of the ALSA control interface from `<sound/asound.h>`.
+const SNDRV_CTL_ELEM_IFACE_MIXER: c_int = 2;
+const SNDRV_CTL_ELEM_TYPE_BOOLEAN: c_int = 1;
+const SNDRV_CTL_ELEM_TYPE_INTEGER: c_int = 2;
+const SNDRV_CTL_ELEM_ID_NAME_MAXLEN: usize = 44;
+
+const SNDRV_CTL_IOCTL_ELEM_INFO: u64 = ioctl_rw(0x11, size_of::<ElemInfo>());
+const SNDRV_CTL_IOCTL_ELEM_READ: u64 = ioctl_rw(0x12, size_of::<ElemValue>());
+const SNDRV_CTL_IOCTL_SUBSCRIBE_EVENTS: u64 = ioctl_rw(0x16, size_of::<c_int>());
+
+/// Size of `struct snd_ctl_event`, used to drain pending events.
+const CTL_EVENT_SIZE: usize = 72;
+
+const _: () = assert!(size_of::<ElemInfo>() == 272);
+#[cfg(target_pointer_width = "64")]
+const _: () = assert!(size_of::<ElemValue>() == 1224);
+
+const fn ioctl_rw(nr: u64, size: usize) -> u64 {
+    (3 << 30) | ((size as u64) << 16) | ((b'U' as u64) << 8) | nr
+}
+
+// Layouts follow the kernel ABI of the target: `long` is `c_long`, and unions with `long long`
+// members get its alignment, e.g. 4 bytes on i686 but 8 on armv7.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct ElemId {
+    _numid: u32,
+    iface: c_int,
+    _device: u32,
+    _subdevice: u32,
+    name: [u8; SNDRV_CTL_ELEM_ID_NAME_MAXLEN],
+    _index: u32,
+}
+
+#[repr(C)]
+struct ElemInfo {
+    id: ElemId,
+    kind: c_int,
+    _access: u32,
+    count: u32,
+    _owner: c_int,
+    value: InfoValue,
+    _reserved: [u8; 64],
+}
+
+#[repr(C)]
+union InfoValue {
+    /// `min`, `max` and `step` for integer elements.
+    integer: [c_long; 3],
+    _integer64: [i64; 3],
+    _reserved: [u8; 128],
+}
+
+#[repr(C)]
+struct ElemValue {
+    id: ElemId,
+    _indirect: u32,
+    value: ValueData,
+    _reserved: [u8; 128],
+}
+
+#[repr(C)]
+union ValueData {
+    /// Integer and boolean elements are both stored as `long` values.
+    integer: [c_long; 128],
+    _integer64: [i64; 64],
+    _data: [u8; 512],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MixerState {
+    /// Volume in percent of the control range, averaged over channels.
+    pub volume: u64,
+    pub muted: bool,
+}
+
+/// Access to a mixer control; abstracted so `Volume` can be tested without sound hardware.
+// Only implemented within the crate, so the futures need no `Send` bound.
+#[allow(async_fn_in_trait)]
+pub trait Mixer {
+    fn state(&mut self) -> Result<MixerState>;
+
+    /// Resolves once the mixer reports a change; pending while no change can be observed.
+    async fn changed(&mut self) -> Result<()>;
+}
+
+#[derive(Debug)]
+pub struct Volume<M: Mixer = AlsaMixer> {
+    mixer: M,
+    /// Shown instead of the volume while the control is muted.
+    muted: &'static str,
+}
+
+impl Volume {
+    pub fn new(card: u32, control: &'static str, muted: &'static str) -> Self {
+        Self {
+            mixer: AlsaMixer::new(card, control),
+            muted,
+        }
+    }
+
+    pub fn control(&self) -> &str {
+        self.mixer.control
+    }
+}
+
+impl<M: Mixer> Volume<M> {
+    pub fn output(&mut self) -> Result<String> {
+        let state = self.mixer.state()?;
+
+        if state.muted {
+            Ok(self.muted.to_string())
+        } else {
+            Ok(state.volume.to_string())
+        }
+    }
+
+    pub async fn changed(&mut self) -> Result<()> {
+        self.mixer.changed().await
+    }
+}
+
+/// Mixer backed by the ALSA control device `/dev/snd/controlC<card>`.
+#[derive(Debug)]
+pub struct AlsaMixer {
+    card: u32,
+    control: &'static str,
+    /// Opened lazily and dropped on errors, so the next read reopens it.
+    device: Option<ControlDevice>,
+}
+
+impl AlsaMixer {
+    const fn new(card: u32, control: &'static str) -> Self {
+        Self {
+            card,
+            control,
+            device: None,
+        }
+    }
+
+    fn path(&self) -> String {
+        format!("/dev/snd/controlC{}", self.card)
+    }
+}
+
+impl Mixer for AlsaMixer {
+    fn state(&mut self) -> Result<MixerState> {
+        let path = self.path();
+
+        let device = match self.device.take() {
+            Some(device) => device,
+            None => ControlDevice::open(&path, self.control)?,
+        };
+
+        let state = device.state(&path)?;
+        self.device = Some(device);
+
+        Ok(state)
+    }
+
+    async fn changed(&mut self) -> Result<()> {
+        let Some(device) = &self.device else {
+            return pending().await;
+        };
+
+        if let Err(err) = device.wait_for_events().await {
+            self.device = None;
+            return Err(Error::io(self.path(), format!("read events: {err}")));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct ControlDevice {
+    fd: AsyncFd<File>,
+    volume: ElemId,
+    channels: u32,
+    min: i64,
+    max: i64,
+    /// Mute switch and its channel count, if the control has one.
+    switch: Option<(ElemId, u32)>,
+}
+
+impl ControlDevice {
+    fn open(path: &str, control: &str) -> Result<Self> {
+        let file = File::options()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)
+            .map_err(|err| Error::io(path, err))?;
+
+        let volume = [
+            format!("{control} Playback Volume"),
+            format!("{control} Volume"),
+        ]
+        .iter()
+        .find_map(|name| {
+            elem_info(&file, name)
+                .ok()
+                .filter(|info| info.kind == SNDRV_CTL_ELEM_TYPE_INTEGER)
+        })
+        .ok_or_else(|| Error::io(path, format!("no volume control `{control}`")))?;
+
+        let switch = [
+            format!("{control} Playback Switch"),
+            format!("{control} Switch"),
+        ]
+        .iter()
+        .find_map(|name| {
+            elem_info(&file, name)
+                .ok()
+                .filter(|info| info.kind == SNDRV_CTL_ELEM_TYPE_BOOLEAN)
+        })
+        .map(|info| (info.id, info.count));
+
+        let mut subscribe: c_int = 1;
+        ioctl(&file, SNDRV_CTL_IOCTL_SUBSCRIBE_EVENTS, &mut subscribe)
+            .map_err(|err| Error::io(path, format!("subscribe events: {err}")))?;
+
+        // SAFETY: integer elements use the `integer` member, and any bytes are valid for it.
+        let [min, max, _] = unsafe { volume.value.integer }.map(i64::from);
+
+        Ok(Self {
+            fd: AsyncFd::new(file).map_err(|err| Error::io(path, err))?,
+            volume: volume.id,
+            channels: volume.count,
+            min,
+            max,
+            switch,
+        })
+    }
+
+    fn state(&self, path: &str) -> Result<MixerState> {
+        let file = self.fd.get_ref();
+
+        let values = elem_read(file, self.volume, self.channels)
+            .map_err(|err| Error::io(path, format!("read volume: {err}")))?;
+        if values.is_empty() {
+            return Err(Error::parse(path, "volume control has no channels"));
+        }
+
+        let sum = values
+            .iter()
+            .map(|value| value.saturating_sub(self.min).max(0) as u64)
+            .sum::<u64>();
+        let range = self.max.saturating_sub(self.min).max(0) as u64;
+        let volume = rounded_percent(sum / values.len() as u64, range)?;
+
+        let muted = match self.switch {
+            Some((switch, channels)) => elem_read(file, switch, channels)
+                .map_err(|err| Error::io(path, format!("read switch: {err}")))?
+                .iter()
+                .all(|&on| on == 0),
+            None => false,
+        };
+
+        Ok(MixerState { volume, muted })
+    }
+
+    async fn wait_for_events(&self) -> io::Result<()> {
+        loop {
+            let mut guard = self.fd.readable().await?;
+
+            match guard.try_io(|fd| drain_events(fd.get_ref())) {
+                Ok(result) => return result,
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+fn drain_events(mut file: &File) -> io::Result<()> {
+    let mut buf = [0u8; CTL_EVENT_SIZE * 16];
+    let mut drained = false;
+
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(_) => drained = true,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock && drained => return Ok(()),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn ioctl<T>(file: &File, request: u64, arg: &mut T) -> io::Result<()> {
+    // SAFETY: `request` encodes the size of `T`, so the kernel stays within `arg`.
+    let res = unsafe { libc::ioctl(file.as_raw_fd(), request as _, arg as *mut T) };
+
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+fn elem_info(file: &File, name: &str) -> io::Result<ElemInfo> {
+    if name.len() >= SNDRV_CTL_ELEM_ID_NAME_MAXLEN {
+        return Err(io::ErrorKind::InvalidInput.into());
+    }
+
+    // SAFETY: plain C struct for which all-zero bytes are valid.
+    let mut info: ElemInfo = unsafe { std::mem::zeroed() };
+    info.id.iface = SNDRV_CTL_ELEM_IFACE_MIXER;
+    info.id.name[..name.len()].copy_from_slice(name.as_bytes());
+
+    ioctl(file, SNDRV_CTL_IOCTL_ELEM_INFO, &mut info)?;
+
+    Ok(info)
+}
+
+fn elem_read(file: &File, id: ElemId, count: u32) -> io::Result<Vec<i64>> {
+    // SAFETY: plain C struct for which all-zero bytes are valid.
+    let mut value: ElemValue = unsafe { std::mem::zeroed() };
+    value.id = id;
+
+    ioctl(file, SNDRV_CTL_IOCTL_ELEM_READ, &mut value)?;
+
+    // SAFETY: integer and boolean elements use the `integer` member, and any bytes are valid
+    // for it.
+    let values = unsafe { value.value.integer };
+    // `long` is already `i64` on 64-bit targets.
+    #[allow(clippy::useless_conversion)]
+    let values = values.into_iter().take(count as usize).map(i64::from);
+    Ok(values.collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeMixer {
+        states: Vec<Result<MixerState>>,
+    }
+
+    impl Mixer for FakeMixer {
+        fn state(&mut self) -> Result<MixerState> {
+            self.states.remove(0)
+        }
+
+        async fn changed(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn volume(states: Vec<Result<MixerState>>) -> Volume<FakeMixer> {
+        Volume {
+            mixer: FakeMixer { states },
+            muted: "muted",
+        }
+    }
+
+    #[test]
+    fn outputs_volume_percent() {
+        let mut volume = volume(vec![Ok(MixerState {
+            volume: 42,
+            muted: false,
+        })]);
+        assert_eq!(volume.output().unwrap(), "42");
+    }
+
+    #[test]
+    fn outputs_muted_text_when_muted() {
+        let mut volume = volume(vec![Ok(MixerState {
+            volume: 42,
+            muted: true,
+        })]);
+        assert_eq!(volume.output().unwrap(), "muted");
+    }
+
+    #[test]
+    fn propagates_mixer_errors() {
+        let mut volume = volume(vec![
+            Err(Error::io("/dev/snd/controlC0", "gone")),
+            Ok(MixerState {
+                volume: 10,
+                muted: false,
+            }),
+        ]);
+        assert!(volume.output().is_err());
+        assert_eq!(volume.output().unwrap(), "10");
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn ioctl_numbers_match_kernel_abi() {
+        assert_eq!(SNDRV_CTL_IOCTL_ELEM_INFO, 0xc110_5511);
+        assert_eq!(SNDRV_CTL_IOCTL_ELEM_READ, 0xc4c8_5512);
+        assert_eq!(SNDRV_CTL_IOCTL_SUBSCRIBE_EVENTS, 0xc004_5516);
+    }
+}
@@ -0,0 +1,194 @@
+use std::future::pending;
+
+use x11rb::{
+    errors::ReplyError,
+    protocol::{
+        Event,
+        xproto::{AtomEnum, ConnectionExt as _, GetPropertyReply},
+    },
+};
+
+use crate::{
+    status::{Error, Result, width::truncate},
+    x11::{EventConnection, intern_atom},
+};
+
+/// Upper bound of property data read per request, in 32-bit units.
+const MAX_PROPERTY_LENGTH: u32 = 1024;
+
+/// Title of the focused window, refreshed on `PropertyNotify` of the root and focused windows.
+#[derive(Debug)]
+pub struct ActiveWindow {
+    /// Maximum display width in cells; longer titles are cut on grapheme boundaries.
+    max_length: usize,
+    /// Dropped on errors, so the next output reconnects.
+    watcher: Option<Box<Watcher>>,
+}
+
+impl ActiveWindow {
+    pub const fn new(max_length: usize) -> Self {
+        Self {
+            max_length,
+            watcher: None,
+        }
+    }
+
+    pub fn output(&mut self) -> Result<String> {
+        let mut watcher = match self.watcher.take() {
+            Some(watcher) => watcher,
+            None => Box::new(Watcher::connect()?),
+        };
+
+        let title = watcher.focused_title()?;
+        self.watcher = Some(watcher);
+
+        Ok(truncate(&title, self.max_length).to_string())
+    }
+
+    pub async fn changed(&mut self) -> Result<()> {
+        let Some(watcher) = &self.watcher else {
+            return pending().await;
+        };
+
+        let changed = loop {
+            match watcher.events.next_event().await {
+                Ok(Event::PropertyNotify(event)) if watcher.is_title_atom(event.atom) => {
+                    break Ok(());
+                }
+                Ok(_) => continue,
+                Err(err) => break Err(err),
+            }
+        };
+
+        if changed.is_err() {
+            self.watcher = None;
+        }
+
+        changed
+    }
+}
+
+#[derive(Debug)]
+struct Watcher {
+    events: EventConnection,
+    net_active_window: u32,
+    net_wm_name: u32,
+    utf8_string: u32,
+    /// Window whose property changes are selected, `0` if none.
+    focused: u32,
+}
+
+impl Watcher {
+    fn connect() -> Result<Self> {
+        let events = EventConnection::connect()?;
+        let net_active_window = intern_atom(events.connection(), "_NET_ACTIVE_WINDOW")?;
+        let net_wm_name = intern_atom(events.connection(), "_NET_WM_NAME")?;
+        let utf8_string = intern_atom(events.connection(), "UTF8_STRING")?;
+
+        events.watch_properties(events.root_window(), true)?;
+
+        Ok(Self {
+            events,
+            net_active_window,
+            net_wm_name,
+            utf8_string,
+            focused: 0,
+        })
+    }
+
+    fn is_title_atom(&self, atom: u32) -> bool {
+        atom == self.net_active_window
+            || atom == self.net_wm_name
+            || atom == u32::from(AtomEnum::WM_NAME)
+    }
+
+    fn focused_title(&mut self) -> Result<String> {
+        let active = self
+            .property(
+                self.events.root_window(),
+                self.net_active_window,
+                AtomEnum::WINDOW.into(),
+            )?
+            .and_then(|reply| reply.value32().and_then(|mut value| value.next()))
+            .unwrap_or(0);
+
+        if active != self.focused {
+            // Either window may already be gone; connection errors surface on the next request.
+            if self.focused != 0 {
+                let _ = self.events.watch_properties(self.focused, false);
+            }
+            if active != 0 {
+                let _ = self.events.watch_properties(active, true);
+            }
+            self.focused = active;
+        }
+
+        if active == 0 {
+            return Ok(String::new());
+        }
+
+        let net_wm_name = self
+            .property(active, self.net_wm_name, self.utf8_string)?
+            .map(|reply| reply.value);
+
+        title(net_wm_name, || {
+            Ok(self
+                .property(active, AtomEnum::WM_NAME.into(), AtomEnum::ANY.into())?
+                .map(|reply| reply.value))
+        })
+    }
+
+    /// Reads property `name` of `window`; `None` if the window no longer exists.
+    fn property(&self, window: u32, name: u32, kind: u32) -> Result<Option<GetPropertyReply>> {
+        let reply = self
+            .events
+            .connection()
+            .get_property(false, window, name, kind, 0, MAX_PROPERTY_LENGTH)
+            .map_err(|err| Error::x11(format!("get property: {err}")))?
+            .reply();
+
+        match reply {
+            Ok(reply) => Ok(Some(reply)),
+            Err(ReplyError::X11Error(_)) => Ok(None),
+            Err(err) => Err(Error::x11(format!("get property reply: {err}"))),
+        }
+    }
+}
+
+/// Title from the raw `_NET_WM_NAME` property value, falling back to the legacy `WM_NAME` read by
+/// `wm_name` if it is missing or empty.
+fn title(
+    net_wm_name: Option<Vec<u8>>,
+    wm_name: impl FnOnce() -> Result<Option<Vec<u8>>>,
+) -> Result<String> {
+    let title = match net_wm_name {
+        Some(value) if !value.is_empty() => value,
+        _ => wm_name()?.unwrap_or_default(),
+    };
+
+    Ok(String::from_utf8_lossy(&title).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::title;
+
+    #[test]
+    fn prefers_net_wm_name() {
+        let title = title(Some("Café – Firefox".into()), || unreachable!()).unwrap();
+        assert_eq!(title, "Café – Firefox");
+    }
+
+    #[test]
+    fn falls_back_to_wm_name() {
+        assert_eq!(
+            title(None, || Ok(Some(b"xterm".to_vec()))).unwrap(),
+            "xterm"
+        );
+        assert_eq!(
+            title(Some(Vec::new()), || Ok(Some(b"xterm".to_vec()))).unwrap(),
+            "xterm"
+        );
+        assert_eq!(title(None, || Ok(None)).unwrap(), "");
+    }
+}
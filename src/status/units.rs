@@ -0,0 +1,144 @@
+const IEC_BYTES: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+const SI_BYTES: &[&str] = &["B", "kB", "MB", "GB", "TB", "PB"];
+const HERTZ: &[&str] = &["Hz", "kHz", "MHz", "GHz", "THz"];
+
+/// Largest precision, so a typo cannot allocate a huge string on every render.
+const MAX_PRECISION: usize = 100;
+/// Largest number of `gauge` cells, for the same reason.
+const MAX_GAUGE_CELLS: usize = 100;
+
+/// Applies `|` separated human readable unit filters such as `bytes:2` to `value`, in order.
+///
+/// Filters take an optional precision after `:`, the number of cells for `gauge`; values that
+/// are not numbers, unknown filters and precisions or cell counts above `MAX_PRECISION` and
+/// `MAX_GAUGE_CELLS` are passed through unchanged.
+pub fn apply_filters(value: &str, filters: &str) -> String {
+    filters
+        .split('|')
+        .filter(|filter| !filter.is_empty())
+        .fold(value.to_string(), |value, filter| {
+            apply_filter(&value, filter).unwrap_or(value)
+        })
+}
+
+fn apply_filter(value: &str, filter: &str) -> Option<String> {
+    let (name, precision) = match filter.split_once(':') {
+        Some((name, precision)) => {
+            let max = if name == "gauge" {
+                MAX_GAUGE_CELLS
+            } else {
+                MAX_PRECISION
+            };
+            (name, Some(precision.parse().ok().filter(|&n| n <= max)?))
+        }
+        None => (filter, None),
+    };
+    let value = value.trim().parse::<f64>().ok()?;
+
+    let formatted = match name {
+        "bytes" => scaled(value, 1024.0, IEC_BYTES, precision.unwrap_or(1)),
+        "si_bytes" => scaled(value, 1000.0, SI_BYTES, precision.unwrap_or(1)),
+        "rate" => format!(
+            "{}/s",
+            scaled(value, 1024.0, IEC_BYTES, precision.unwrap_or(1))
+        ),
+        "freq" => scaled(value, 1000.0, HERTZ, precision.unwrap_or(1)),
+        "temp" => format!("{value:.*}°C", precision.unwrap_or(0)),
+        "duration" => duration(value),
+        "gauge" => gauge(value, precision.unwrap_or(10)),
+        _ => return None,
+    };
+
+    Some(formatted)
+}
+
+/// `value` in the largest unit below it, e.g. `1.5 MiB`; the base unit has no decimals.
+fn scaled(value: f64, step: f64, units: &[&str], precision: usize) -> String {
+    let mut value = value;
+    let mut unit = 0;
+
+    while value.abs() >= step && unit + 1 < units.len() {
+        value /= step;
+        unit += 1;
+    }
+
+    let precision = if unit == 0 { 0 } else { precision };
+    format!("{value:.precision$} {}", units[unit])
+}
+
+/// Percentage as a horizontal bar of `cells` cells, e.g. `[████░░]`.
+fn gauge(percent: f64, cells: usize) -> String {
+    let filled = ((percent.clamp(0.0, 100.0) / 100.0) * cells as f64).round() as usize;
+    format!("[{}{}]", "█".repeat(filled), "░".repeat(cells - filled))
+}
+
+/// Seconds as the two largest units, e.g. `1h 05m`, `5m 03s` or `42s`.
+fn duration(seconds: f64) -> String {
+    let seconds = seconds.max(0.0).round() as u64;
+    let (days, hours, minutes, seconds) = (
+        seconds / 86400,
+        seconds / 3600 % 24,
+        seconds / 60 % 60,
+        seconds % 60,
+    );
+
+    if days > 0 {
+        format!("{days}d {hours:02}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes:02}m")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds:02}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply_filters;
+
+    #[test]
+    fn formats_bytes() {
+        assert_eq!(apply_filters("512", "bytes"), "512 B");
+        assert_eq!(apply_filters("1536", "bytes"), "1.5 KiB");
+        assert_eq!(apply_filters("1572864", "bytes:2"), "1.50 MiB");
+        assert_eq!(apply_filters("1500000", "si_bytes"), "1.5 MB");
+        assert_eq!(apply_filters("2048", "rate:0"), "2 KiB/s");
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(apply_filters("42", "duration"), "42s");
+        assert_eq!(apply_filters("303", "duration"), "5m 03s");
+        assert_eq!(apply_filters("3900", "duration"), "1h 05m");
+        assert_eq!(apply_filters("183600", "duration"), "2d 03h");
+    }
+
+    #[test]
+    fn formats_frequencies_and_temperatures() {
+        assert_eq!(apply_filters("2400000000", "freq"), "2.4 GHz");
+        assert_eq!(apply_filters("45.6", "temp"), "46°C");
+        assert_eq!(apply_filters("45.6", "temp:1"), "45.6°C");
+    }
+
+    #[test]
+    fn renders_gauges() {
+        assert_eq!(apply_filters("66", "gauge:6"), "[████░░]");
+        assert_eq!(apply_filters("150", "gauge:2"), "[██]");
+        assert_eq!(apply_filters("0", "gauge"), "[░░░░░░░░░░]");
+        assert_eq!(apply_filters("50", "gauge:1000000"), "50");
+    }
+
+    #[test]
+    fn passes_through_non_numbers_and_unknown_filters() {
+        assert_eq!(apply_filters("muted", "bytes"), "muted");
+        assert_eq!(apply_filters("42", "unknown"), "42");
+        assert_eq!(apply_filters("42", "bytes:x"), "42");
+        assert_eq!(apply_filters("42", "temp:101"), "42");
+    }
+
+    #[test]
+    fn chains_filters() {
+        assert_eq!(apply_filters("2048", "bytes|unknown"), "2.0 KiB");
+    }
+}
@@ -3,6 +3,7 @@ use tokio::{
     io::{AsyncBufReadExt, BufReader},
 };
 
+use super::units::apply_filters;
 use crate::error::{Error, Result};
 
 pub async fn read_line(from: &str) -> Result<String> {
@@ -53,9 +54,45 @@ pub fn rounded_percent(numerator: u64, denominator: u64) -> Result<u64> {
     Ok(capped)
 }
 
+/// Replaces `{name}` placeholders in `template` with the value returned by `field`, passed
+/// through the filters of `{name|filter|...}` placeholders; placeholders for which `field`
+/// returns `None` are kept verbatim.
+pub fn fill_template(template: &str, field: impl Fn(&str) -> Option<String>) -> String {
+    fill_filtered(template, |name, filters| {
+        Some(apply_filters(&field(name)?, filters))
+    })
+}
+
+/// Like `fill_template`, leaving the `|` separated filters of a placeholder to `field`.
+pub fn fill_filtered(template: &str, field: impl Fn(&str, &str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+
+        let placeholder = &rest[1..end];
+        let (name, filters) = placeholder.split_once('|').unwrap_or((placeholder, ""));
+
+        match field(name, filters) {
+            Some(value) => output.push_str(&value),
+            None => output.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    output.push_str(rest);
+
+    output
+}
+
 #[cfg(test)]
 mod tests {
-    use super::rounded_percent;
+    use super::{fill_template, rounded_percent};
 
     #[test]
     fn rounds_to_nearest_percent() {
@@ -77,4 +114,30 @@ mod tests {
     fn zero_denominator_errors() {
         assert!(rounded_percent(1, 0).is_err());
     }
+
+    #[test]
+    fn fills_known_placeholders() {
+        let filled = fill_template("{artist} - {title}", |name| match name {
+            "artist" => Some("A".to_string()),
+            "title" => Some("T".to_string()),
+            _ => None,
+        });
+        assert_eq!(filled, "A - T");
+    }
+
+    #[test]
+    fn applies_filters_to_fields() {
+        let filled = fill_template("{rx|bytes}/s {left|duration}", |name| match name {
+            "rx" => Some("1536".to_string()),
+            "left" => Some("3900".to_string()),
+            _ => None,
+        });
+        assert_eq!(filled, "1.5 KiB/s 1h 05m");
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders_and_unclosed_braces() {
+        let filled = fill_template("{x} {} {y", |_| None);
+        assert_eq!(filled, "{x} {} {y");
+    }
 }
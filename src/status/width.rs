@@ -0,0 +1,239 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Shown at the end of values cut by `Overflow::Ellipsis`.
+const ELLIPSIS: &str = "…";
+/// Space between the end and the restart of a scrolling value.
+const MARQUEE_GAP: &str = "   ";
+
+/// Side of the padding added to values narrower than `Width::min`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    /// Value first, padded on the right.
+    Left,
+    /// Padded on the left, value last.
+    Right,
+    /// Padded on both sides, one more space on the right if uneven.
+    Center,
+}
+
+/// How values wider than `Width::max` are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Cut off at `max`.
+    Cut,
+    /// Cut off with a trailing `…`.
+    Ellipsis,
+    /// Scrolled by one character on every bar write.
+    Marquee,
+}
+
+/// Display width limits of a status value, counted in terminal cells, so wide glyphs such as
+/// CJK count as two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Width {
+    /// Narrower values are padded with spaces.
+    pub min: usize,
+    /// Wider values are handled by `overflow`; cut on grapheme boundaries.
+    pub max: Option<usize>,
+    pub alignment: Alignment,
+    pub overflow: Overflow,
+}
+
+impl Width {
+    pub fn apply(&self, value: &str) -> String {
+        let value = match self.max {
+            Some(max) if value.width() > max => match self.overflow {
+                Overflow::Cut | Overflow::Marquee => truncate(value, max).to_string(),
+                Overflow::Ellipsis => {
+                    let value = truncate(value, max.saturating_sub(ELLIPSIS.width()));
+                    format!("{value}{ELLIPSIS}")
+                }
+            },
+            _ => value.to_string(),
+        };
+
+        let padding = self.min.saturating_sub(value.width());
+        let (left, right) = match self.alignment {
+            Alignment::Left => (0, padding),
+            Alignment::Right => (padding, 0),
+            Alignment::Center => (padding / 2, padding - padding / 2),
+        };
+
+        format!("{}{value}{}", " ".repeat(left), " ".repeat(right))
+    }
+
+    /// Scrolling window over `value`, if it is too wide and `overflow` is `Marquee`.
+    pub fn marquee(&self, value: &str) -> Option<Marquee> {
+        match self.max {
+            Some(max) if self.overflow == Overflow::Marquee && value.width() > max => {
+                Some(Marquee {
+                    graphemes: value
+                        .graphemes(true)
+                        .chain(MARQUEE_GAP.graphemes(true))
+                        .map(String::from)
+                        .collect(),
+                    width: max,
+                    offset: 0,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Longest prefix of `value` at most `max` cells wide, cut on grapheme boundaries.
+pub(crate) fn truncate(value: &str, max: usize) -> &str {
+    let mut width = 0;
+
+    for (index, grapheme) in value.grapheme_indices(true) {
+        width += grapheme.width();
+        if width > max {
+            return &value[..index];
+        }
+    }
+
+    value
+}
+
+/// Value wider than its `Width::max`, shown as a window scrolling over the value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Marquee {
+    /// Graphemes of the value followed by the gap.
+    graphemes: Vec<String>,
+    width: usize,
+    /// First grapheme in the window.
+    offset: usize,
+}
+
+impl Marquee {
+    /// Takes over the position of `previous` if it scrolls the same text in the same width.
+    pub fn continue_from(&mut self, previous: &Self) {
+        if self.graphemes == previous.graphemes && self.width == previous.width {
+            self.offset = previous.offset;
+        }
+    }
+
+    /// Moves the window by one grapheme, wrapping around after the gap.
+    pub fn advance(&mut self) {
+        self.offset = (self.offset + 1) % self.graphemes.len().max(1);
+    }
+
+    /// Current window, padded to exactly `width` cells.
+    pub fn window(&self) -> String {
+        let mut window = String::new();
+        let mut width = 0;
+
+        for grapheme in self
+            .graphemes
+            .iter()
+            .cycle()
+            .skip(self.offset)
+            .take(self.graphemes.len())
+        {
+            width += grapheme.width();
+            if width > self.width {
+                width -= grapheme.width();
+                break;
+            }
+            window.push_str(grapheme);
+        }
+
+        window.push_str(&" ".repeat(self.width - width));
+        window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Alignment, Overflow, Width, truncate};
+
+    fn width(min: usize, max: Option<usize>, alignment: Alignment) -> Width {
+        Width {
+            min,
+            max,
+            alignment,
+            overflow: Overflow::Cut,
+        }
+    }
+
+    #[test]
+    fn pads_to_min_width() {
+        assert_eq!(width(3, None, Alignment::Right).apply("9"), "  9");
+        assert_eq!(width(3, None, Alignment::Left).apply("9"), "9  ");
+        assert_eq!(width(4, None, Alignment::Center).apply("9"), " 9  ");
+        assert_eq!(width(2, None, Alignment::Right).apply("100"), "100");
+    }
+
+    #[test]
+    fn counts_display_width() {
+        // CJK characters take two cells.
+        assert_eq!(width(6, None, Alignment::Left).apply("東京"), "東京  ");
+        assert_eq!(width(0, Some(3), Alignment::Left).apply("東京"), "東");
+    }
+
+    #[test]
+    fn cuts_to_max_width() {
+        assert_eq!(width(0, Some(4), Alignment::Left).apply("abcdef"), "abcd");
+        assert_eq!(width(5, Some(4), Alignment::Right).apply("abcdef"), " abcd");
+    }
+
+    #[test]
+    fn cuts_on_grapheme_boundaries_with_ellipsis() {
+        let width = Width {
+            overflow: Overflow::Ellipsis,
+            ..width(0, Some(4), Alignment::Left)
+        };
+
+        assert_eq!(width.apply("abcdef"), "abc…");
+        // `e` with a combining acute accent stays one grapheme.
+        assert_eq!(width.apply("abe\u{301}def"), "abe\u{301}…");
+        assert_eq!(width.apply("abcd"), "abcd");
+    }
+
+    #[test]
+    fn truncates_to_display_width() {
+        // `e` with a combining acute accent stays one grapheme, CJK characters take two cells.
+        assert_eq!(truncate("cafe\u{301} au lait", 5), "cafe\u{301} ");
+        assert_eq!(truncate("東京タワー", 5), "東京");
+    }
+
+    #[test]
+    fn marquee_scrolls_and_wraps_around() {
+        let width = Width {
+            overflow: Overflow::Marquee,
+            ..width(0, Some(4), Alignment::Left)
+        };
+        assert_eq!(width.marquee("abcd"), None);
+
+        let mut marquee = width.marquee("abcdef").unwrap();
+        assert_eq!(marquee.window(), "abcd");
+
+        marquee.advance();
+        assert_eq!(marquee.window(), "bcde");
+
+        for _ in 0..4 {
+            marquee.advance();
+        }
+        assert_eq!(marquee.window(), "f   ");
+
+        for _ in 0..4 {
+            marquee.advance();
+        }
+        assert_eq!(marquee.window(), "abcd");
+    }
+
+    #[test]
+    fn marquee_pads_split_wide_graphemes() {
+        let width = Width {
+            overflow: Overflow::Marquee,
+            ..width(0, Some(3), Alignment::Left)
+        };
+
+        let marquee = width.marquee("a東京").unwrap();
+        assert_eq!(marquee.window(), "a東");
+
+        let marquee = width.marquee("東京a").unwrap();
+        assert_eq!(marquee.window(), "東 ");
+    }
+}
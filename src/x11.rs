@@ -1,16 +1,51 @@
+use std::{
+    os::fd::{AsRawFd, RawFd},
+    time::Duration,
+};
+
+use tokio::io::unix::AsyncFd;
 use x11rb::{
-    connection::Connection,
-    protocol::xproto::{AtomEnum, ConnectionExt, PropMode},
+    connection::{Connection, RequestConnection},
+    protocol::{
+        Event,
+        dpms::{self, ConnectionExt as _},
+        screensaver::{self, ConnectionExt as _},
+        xproto::{AtomEnum, ChangeWindowAttributesAux, ConnectionExt, EventMask, PropMode},
+    },
     rust_connection::RustConnection,
 };
 
-use crate::error::{Error, Result};
+use crate::{
+    error::{Error, Result},
+    log,
+};
+
+/// Connects to the default display, returning the connection and its root window.
+fn connect() -> Result<(RustConnection, u32)> {
+    let (connection, screen_num) =
+        x11rb::connect(None).map_err(|err| Error::x11(format!("connect: {}", err)))?;
+    let root_window = connection.setup().roots[screen_num].root;
+
+    Ok((connection, root_window))
+}
+
+pub fn intern_atom(connection: &RustConnection, name: &str) -> Result<u32> {
+    Ok(connection
+        .intern_atom(false, name.as_bytes())
+        .map_err(|err| Error::x11(format!("intern atom: {}", err)))?
+        .reply()
+        .map_err(|err| Error::x11(format!("intern atom reply: {}", err)))?
+        .atom)
+}
 
 #[derive(Debug)]
 pub struct X11rb {
     connection: RustConnection,
     root_window: u32,
     name_atom: u32,
+    /// Idle detection uses whichever of the MIT-SCREEN-SAVER and DPMS extensions is present.
+    has_screensaver: bool,
+    has_dpms: bool,
     consecutive_failures: u32,
     max_failures_before_reconnect: u32,
 }
@@ -21,22 +56,23 @@ impl X11rb {
     }
 
     fn connect(max_failures_before_reconnect: u32) -> Result<Self> {
-        let (connection, screen_num) =
-            x11rb::connect(None).map_err(|err| Error::x11(format!("connect: {}", err)))?;
-        let screen = &connection.setup().roots[screen_num];
-        let root_window = screen.root;
-
-        let name_atom = connection
-            .intern_atom(false, b"WM_NAME")
-            .map_err(|err| Error::x11(format!("intern atom: {}", err)))?
-            .reply()
-            .map_err(|err| Error::x11(format!("intern atom reply: {}", err)))?
-            .atom;
+        let (connection, root_window) = connect()?;
+        let name_atom = intern_atom(&connection, "WM_NAME")?;
+        let has_extension = |name| {
+            connection
+                .extension_information(name)
+                .map(|info| info.is_some())
+                .map_err(|err| Error::x11(format!("query extension `{}`: {}", name, err)))
+        };
+        let has_screensaver = has_extension(screensaver::X11_EXTENSION_NAME)?;
+        let has_dpms = has_extension(dpms::X11_EXTENSION_NAME)?;
 
         Ok(Self {
             connection,
             root_window,
             name_atom,
+            has_screensaver,
+            has_dpms,
             consecutive_failures: 0,
             max_failures_before_reconnect,
         })
@@ -56,22 +92,25 @@ impl X11rb {
             }
             Err(err) => {
                 self.consecutive_failures += 1;
-                eprintln!("error writing root window name: {err}");
+                log::warn(None, format!("error writing root window name: {err}"));
 
                 if self.consecutive_failures >= self.max_failures_before_reconnect {
-                    eprintln!(
-                        "X11 write failed {} times consecutively, attempting reconnect...",
-                        self.consecutive_failures
+                    log::warn(
+                        None,
+                        format!(
+                            "X11 write failed {} times consecutively, attempting reconnect...",
+                            self.consecutive_failures
+                        ),
                     );
 
                     match self.reconnect() {
                         Ok(()) => {
-                            eprintln!("X11 reconnection successful");
+                            log::info(None, "X11 reconnection successful");
                             // Try writing again after successful reconnect
                             self.try_set_root_win_name(name)
                         }
                         Err(reconnect_err) => {
-                            eprintln!("X11 reconnection failed: {reconnect_err}");
+                            log::error(None, format!("X11 reconnection failed: {reconnect_err}"));
                             Err(reconnect_err)
                         }
                     }
@@ -82,6 +121,41 @@ impl X11rb {
         }
     }
 
+    /// Whether the screen saver is active, DPMS turned the monitor off, or there was no
+    /// user input for `idle_after`.
+    pub fn is_idle(&self, idle_after: Option<Duration>) -> Result<bool> {
+        if self.has_screensaver {
+            let info = self
+                .connection
+                .screensaver_query_info(self.root_window)
+                .map_err(|err| Error::x11(format!("screen saver query info: {}", err)))?
+                .reply()
+                .map_err(|err| Error::x11(format!("screen saver query info reply: {}", err)))?;
+
+            let since_input = Duration::from_millis(info.ms_since_user_input.into());
+            if info.state == u8::from(screensaver::State::ON)
+                || idle_after.is_some_and(|idle_after| since_input >= idle_after)
+            {
+                return Ok(true);
+            }
+        }
+
+        if self.has_dpms {
+            let info = self
+                .connection
+                .dpms_info()
+                .map_err(|err| Error::x11(format!("dpms info: {}", err)))?
+                .reply()
+                .map_err(|err| Error::x11(format!("dpms info reply: {}", err)))?;
+
+            if info.state && info.power_level != dpms::DPMSMode::ON {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
     fn try_set_root_win_name(&self, name: &str) -> Result<()> {
         self.connection
             .change_property(
@@ -103,3 +177,71 @@ impl X11rb {
         Ok(())
     }
 }
+
+/// Separate connection for event-driven sources; waits for events without blocking the runtime.
+#[derive(Debug)]
+pub struct EventConnection {
+    /// Declared before `connection`, so it is deregistered before the socket is closed.
+    fd: AsyncFd<RawFd>,
+    connection: RustConnection,
+    root_window: u32,
+}
+
+impl EventConnection {
+    pub fn connect() -> Result<Self> {
+        let (connection, root_window) = connect()?;
+        let fd = AsyncFd::new(connection.stream().as_raw_fd())
+            .map_err(|err| Error::x11(format!("register connection: {}", err)))?;
+
+        Ok(Self {
+            fd,
+            connection,
+            root_window,
+        })
+    }
+
+    pub const fn connection(&self) -> &RustConnection {
+        &self.connection
+    }
+
+    pub const fn root_window(&self) -> u32 {
+        self.root_window
+    }
+
+    /// Selects (or deselects) `PropertyNotify` events of `window`.
+    pub fn watch_properties(&self, window: u32, watch: bool) -> Result<()> {
+        let event_mask = if watch {
+            EventMask::PROPERTY_CHANGE
+        } else {
+            EventMask::NO_EVENT
+        };
+
+        self.connection
+            .change_window_attributes(
+                window,
+                &ChangeWindowAttributesAux::new().event_mask(event_mask),
+            )
+            .map_err(|err| Error::x11(format!("change window attributes: {}", err)))?
+            .check()
+            .map_err(|err| Error::x11(format!("change window attributes reply: {}", err)))
+    }
+
+    /// Waits for the next event, including events already queued by earlier replies.
+    pub async fn next_event(&self) -> Result<Event> {
+        loop {
+            if let Some(event) = self
+                .connection
+                .poll_for_event()
+                .map_err(|err| Error::x11(format!("poll for event: {}", err)))?
+            {
+                return Ok(event);
+            }
+
+            self.fd
+                .readable()
+                .await
+                .map_err(|err| Error::x11(format!("wait for event: {}", err)))?
+                .clear_ready();
+        }
+    }
+}